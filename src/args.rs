@@ -1,5 +1,13 @@
 use crate::i18n::I18n;
-use crate::writer::OutputFormat;
+use passwg::charset::{AMBIGUOUS, Charset};
+use passwg::generator::CHARSET_FAST;
+use passwg::hash::HashAlgo;
+use passwg::pattern::Pattern;
+use passwg::phrase::{Case, PhraseStyle, Separator};
+use passwg::policy::Policy;
+use passwg::words::Wordlist;
+use passwg::writer::OutputFormat;
+use passwg::{Generator, GeneratorBuilder, Mode};
 
 pub struct Config {
     pub length: usize,
@@ -11,9 +19,446 @@ pub struct Config {
     pub word_mode: bool,
     pub out_file: Option<String>,
     pub format: OutputFormat,
+    pub delimiter: u8,
+    pub name_template: Option<String>,
+    pub username: Option<String>,
+    pub hash: Option<HashAlgo>,
+    pub hash_only: bool,
+    pub hibp: Option<String>,
+    pub charset: Charset,
+    pub policy: Policy,
+    pub pattern: Option<Pattern>,
+    pub target_bits: Option<f64>,
+    pub wordlist: Wordlist,
+    pub phrase: PhraseStyle,
+    pub seed: Option<[u8; 32]>,
+    pub ordered: bool,
+    pub clear_after: Option<u64>,
 }
 
-pub fn parse_args(args: &[String]) -> Config {
+impl Config {
+    /// Настройки генератора из аргументов командной строки
+    pub fn builder(&self) -> GeneratorBuilder {
+        let mode = if let Some(pattern) = &self.pattern {
+            Mode::Pattern(pattern.clone())
+        } else if self.word_mode {
+            Mode::Words
+        } else if self.fast_mode {
+            Mode::Fast
+        } else {
+            Mode::Chars
+        };
+
+        let mut builder = Generator::builder()
+            .length(self.length)
+            .mode(mode)
+            .rounds(self.rounds)
+            .charset(self.charset.clone())
+            .policy(self.policy.clone())
+            .wordlist(self.wordlist.clone())
+            .phrase(self.phrase.clone())
+            .format(self.format)
+            .delimiter(self.delimiter);
+        if let Some(template) = &self.name_template {
+            builder = builder.name_template(template.as_str());
+        }
+        if let Some(username) = &self.username {
+            builder = builder.username(username.as_str());
+        }
+        if let Some(path) = &self.hibp {
+            builder = builder.breach_db(path.as_str());
+        }
+        if let Some(algo) = self.hash {
+            builder = builder.hash(algo).hash_only(self.hash_only);
+        }
+        if let Some(bits) = self.target_bits {
+            builder = builder.target_bits(bits);
+        }
+        if let Some(seed) = self.seed {
+            builder = builder.seed(seed);
+        }
+        builder
+    }
+}
+
+/// Ошибка разбора командной строки. main печатает ее как `Ошибка: ...` и выходит с кодом 1
+#[derive(Debug, PartialEq)]
+pub enum ArgError {
+    /// Среди аргументов встретился `-h` / `--help`
+    Help,
+    UnknownFlag(String),
+    /// Флагу не хватило значения
+    MissingValue {
+        flag: String,
+        expected: &'static str,
+        example: Option<&'static str>,
+    },
+    /// Значение через `=` у флага, который его не принимает: `--stats=1`
+    UnexpectedValue(String),
+    /// Неверное значение флага или несовместимые флаги
+    Invalid(String),
+    /// Позиционный аргумент — не число
+    NotANumber(String),
+}
+
+impl std::fmt::Display for ArgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ArgError::Help => write!(f, "запрошена справка"),
+            ArgError::UnknownFlag(flag) => write!(
+                f,
+                "неизвестный флаг '{}'\nИспользуйте -h для просмотра доступных флагов",
+                flag
+            ),
+            ArgError::MissingValue {
+                flag,
+                expected,
+                example,
+            } => {
+                write!(f, "флаг {} требует {}", flag, expected)?;
+                match example {
+                    Some(example) => write!(f, "\nПример: {}", example),
+                    None => Ok(()),
+                }
+            }
+            ArgError::UnexpectedValue(flag) => write!(f, "флаг {} не принимает значения", flag),
+            ArgError::Invalid(message) => write!(f, "{}", message),
+            ArgError::NotANumber(arg) => write!(
+                f,
+                "неверный аргумент '{}'\nАргументы должны быть числами или флагами\nПример: passwg 20 5 -s",
+                arg
+            ),
+        }
+    }
+}
+
+/// Аргумент после разбора: флаг (`-s`, `--rounds`) или позиционный
+enum Arg {
+    Flag(String),
+    Positional(String),
+}
+
+/// Разбор в стиле GNU: `--flag value` и `--flag=value`, склеенные короткие флаги
+/// (`-sf`, `-r20`, `-ofile`) и `--` как конец флагов. Значение флага берется
+/// как есть, даже если начинается с `-`: `--charset -_a-z` работает как раньше
+struct Parser<'a> {
+    args: &'a [String],
+    pos: usize,
+    /// Еще не разобранный хвост склейки коротких флагов
+    cluster: String,
+    /// Значение из `--flag=value`
+    attached: Option<String>,
+    /// Текущий флаг, для сообщений об ошибках
+    flag: String,
+    positional_only: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn new(args: &'a [String], start: usize) -> Self {
+        Parser {
+            args,
+            pos: start,
+            cluster: String::new(),
+            attached: None,
+            flag: String::new(),
+            positional_only: false,
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<Arg>, ArgError> {
+        // Значение после `=` никто не забрал — флаг его не принимает
+        if self.attached.is_some() {
+            return Err(ArgError::UnexpectedValue(self.flag.clone()));
+        }
+        if let Some(c) = self.cluster.chars().next() {
+            self.cluster.drain(..c.len_utf8());
+            self.flag = format!("-{}", c);
+            return Ok(Some(Arg::Flag(self.flag.clone())));
+        }
+        while let Some(raw) = self.args.get(self.pos) {
+            self.pos += 1;
+            if self.positional_only || raw == "-" || !raw.starts_with('-') {
+                return Ok(Some(Arg::Positional(raw.clone())));
+            }
+            if raw == "--" {
+                self.positional_only = true;
+                continue;
+            }
+            if let Some(long) = raw.strip_prefix("--") {
+                let (name, value) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (long, None),
+                };
+                self.flag = format!("--{}", name);
+                self.attached = value;
+            } else {
+                let mut chars = raw[1..].chars();
+                let first = chars.next().unwrap_or('-');
+                self.cluster = chars.as_str().to_string();
+                self.flag = format!("-{}", first);
+            }
+            return Ok(Some(Arg::Flag(self.flag.clone())));
+        }
+        Ok(None)
+    }
+
+    /// Значение текущего флага: после `=`, остаток склейки (`-r20`) или следующий аргумент
+    fn value(&mut self, expected: &'static str, example: Option<&'static str>) -> Result<String, ArgError> {
+        if let Some(value) = self.attached.take() {
+            return Ok(value);
+        }
+        if !self.cluster.is_empty() {
+            return Ok(std::mem::take(&mut self.cluster));
+        }
+        match self.args.get(self.pos) {
+            Some(value) => {
+                self.pos += 1;
+                Ok(value.clone())
+            }
+            None => Err(ArgError::MissingValue {
+                flag: self.flag.clone(),
+                expected,
+                example,
+            }),
+        }
+    }
+
+    /// Число больше 0; `message` — ошибка для всего остального
+    fn number<T: std::str::FromStr + Default + PartialOrd>(
+        &mut self,
+        expected: &'static str,
+        message: &str,
+    ) -> Result<T, ArgError> {
+        match self.value(expected, None)?.parse::<T>() {
+            Ok(n) if n > T::default() => Ok(n),
+            _ => Err(ArgError::Invalid(message.to_string())),
+        }
+    }
+}
+
+/// Граница, которую `profile::apply` ставит после флагов из файла настроек
+/// и окружения. Флаги до нее — умолчания: предупреждения о том, что флаг
+/// не действует в выбранном режиме, для них не печатаются
+pub const CONFIG_END: &str = "__config-end";
+
+/// Флаги без значения, допустимые в файле настроек, и флаг для `ключ = false`
+pub const SWITCHES: &[(&str, Option<&str>)] = &[
+    ("stats", Some("no-stats")),
+    ("fast", Some("no-fast")),
+    ("copy", Some("no-copy")),
+    ("words", Some("no-words")),
+    ("no-ambiguous", Some("ambiguous")),
+    ("ordered", Some("unordered")),
+    ("unordered", Some("ordered")),
+    ("append-digit", Some("no-append-digit")),
+    ("append-symbol", Some("no-append-symbol")),
+    ("hash-only", Some("no-hash-only")),
+    ("plain", None),
+    ("json", Some("plain")),
+    ("json-objects", Some("plain")),
+    ("ndjson", Some("plain")),
+    ("csv", Some("plain")),
+    ("tsv", Some("plain")),
+    ("keepass-csv", Some("plain")),
+    ("keepass-xml", Some("plain")),
+    ("bitwarden", Some("plain")),
+    ("1password", Some("plain")),
+];
+
+/// Флаги со значением, допустимые в файле настроек
+pub const OPTIONS: &[&str] = &[
+    "length", "count", "rounds", "output", "charset", "require", "policy", "pattern", "bits", "wordlist", "sep",
+    "case", "hash", "hibp", "delimiter", "name-template", "username", "clear-after",
+];
+
+/// Длинное имя флага: под ним флаг учитывается независимо от формы записи
+fn long_name(flag: &str) -> &str {
+    match flag {
+        "-s" => "--stats",
+        "-f" => "--fast",
+        "-c" => "--copy",
+        "-w" => "--words",
+        "-o" => "--output",
+        "-r" => "--rounds",
+        flag => flag,
+    }
+}
+
+pub struct DeriveArgs {
+    pub site: String,
+    pub login: String,
+    pub counter: u32,
+    /// Остальные аргументы — обычные флаги генерации
+    pub config: Config,
+}
+
+/// Разбирает `derive <сайт> [--login L] [--counter N] ...`.
+/// Сайт — строго первый аргумент после derive, чтобы не спутать его со значением флага.
+/// Остальные аргументы передаются в `parse_args` как есть, поэтому здесь
+/// разбираются только собственные флаги derive, в том числе в форме `--login=L`.
+/// derive выводит ровно один пароль в терминал или буфер обмена, так что файл,
+/// количество, форматы и хеш отклоняются, а не игнорируются молча
+pub fn parse_derive(args: &[String]) -> Result<DeriveArgs, ArgError> {
+    let site = match args.get(2) {
+        Some(s) if !s.starts_with('-') => s.clone(),
+        _ => {
+            return Err(ArgError::Invalid(
+                "derive требует имени сайта\nПример: passwg derive example.com --login me@example.com 20".into(),
+            ));
+        }
+    };
+    let mut login = String::new();
+    let mut counter = 1;
+    let mut rest = vec![args[0].clone()];
+    let mut i = 3;
+
+    while i < args.len() {
+        let arg = args[i].as_str();
+        if arg == "--" {
+            rest.extend_from_slice(&args[i..]);
+            break;
+        }
+        let (name, attached) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg, None),
+        };
+        match name {
+            "--login" | "--counter" => {
+                let value = match attached {
+                    Some(value) => value,
+                    None => {
+                        i += 1;
+                        args.get(i).cloned().ok_or(ArgError::MissingValue {
+                            flag: name.to_string(),
+                            expected: if name == "--login" {
+                                "указания логина"
+                            } else {
+                                "указания числа"
+                            },
+                            example: None,
+                        })?
+                    }
+                };
+                if name == "--login" {
+                    login = value;
+                } else {
+                    counter = value
+                        .parse()
+                        .map_err(|_| ArgError::Invalid("неверное значение --counter".into()))?;
+                }
+            }
+            "--seed" => return Err(ArgError::Invalid("флаг --seed не используется с derive".into())),
+            // Пароль derive всегда один и тот же, перебирать кандидатов нельзя
+            "--hibp" => {
+                return Err(ArgError::Invalid(
+                    "флаг --hibp не используется с derive\nПроверьте пароль отдельно: passwg check --hibp ФАЙЛ".into(),
+                ));
+            }
+            _ => rest.push(arg.to_string()),
+        }
+        i += 1;
+    }
+
+    let config = parse_args(&rest)?;
+    let unsupported = if config.out_file.is_some() {
+        Some("флаг -o")
+    } else if config.count != 1 {
+        Some("количество паролей")
+    } else if config.format != OutputFormat::Plain {
+        Some("формат вывода")
+    } else if config.hash.is_some() {
+        Some("флаг --hash")
+    } else {
+        None
+    };
+    if let Some(what) = unsupported {
+        return Err(ArgError::Invalid(format!(
+            "{} не используется с derive: derive выводит один пароль\nПример: passwg derive example.com 20 -c",
+            what
+        )));
+    }
+
+    Ok(DeriveArgs {
+        site,
+        login,
+        counter,
+        config,
+    })
+}
+
+pub struct CheckArgs {
+    /// Локальный файл Pwned Passwords
+    pub hibp: Option<String>,
+    /// Файл с паролями по одному на строку; без него читаем stdin
+    pub input: Option<String>,
+    pub format: OutputFormat,
+}
+
+/// Разбирает `check [--hibp ФАЙЛ] [--json|--ndjson|--csv] [ФАЙЛ]`
+pub fn parse_check(args: &[String]) -> Result<CheckArgs, ArgError> {
+    let mut hibp = None;
+    let mut input = None;
+    let mut format = OutputFormat::Plain;
+    let mut p = Parser::new(args, 2);
+
+    while let Some(arg) = p.next()? {
+        match arg {
+            Arg::Flag(flag) => match flag.as_str() {
+                "--json" => format = OutputFormat::Json,
+                "--ndjson" => format = OutputFormat::Ndjson,
+                "--csv" => format = OutputFormat::Csv,
+                "--hibp" => hibp = Some(p.value("пути к файлу Pwned Passwords", None)?),
+                "-h" | "--help" => return Err(ArgError::Help),
+                _ => return Err(ArgError::UnknownFlag(flag)),
+            },
+            Arg::Positional(path) if path == "-" => input = None,
+            Arg::Positional(path) => input = Some(path),
+        }
+    }
+
+    Ok(CheckArgs {
+        hibp,
+        input,
+        format,
+    })
+}
+
+pub struct ValidateArgs {
+    pub policy: Policy,
+    /// Файл с паролями по одному на строку; без него читаем stdin
+    pub input: Option<String>,
+}
+
+/// Разбирает `validate --policy ФАЙЛ [ФАЙЛ]`
+pub fn parse_validate(args: &[String]) -> Result<ValidateArgs, ArgError> {
+    let mut policy = None;
+    let mut input = None;
+    let mut p = Parser::new(args, 2);
+
+    while let Some(arg) = p.next()? {
+        match arg {
+            Arg::Flag(flag) => match flag.as_str() {
+                "--policy" => {
+                    let path = p.value("пути к файлу политики", None)?;
+                    policy = Some(Policy::load(&path).map_err(ArgError::Invalid)?);
+                }
+                "-h" | "--help" => return Err(ArgError::Help),
+                _ => return Err(ArgError::UnknownFlag(flag)),
+            },
+            Arg::Positional(path) if path == "-" => input = None,
+            Arg::Positional(path) => input = Some(path),
+        }
+    }
+
+    let policy = policy.ok_or_else(|| {
+        ArgError::Invalid(
+            "validate требует файла политики\nПример: passwg validate --policy policy.toml < passwords.txt".into(),
+        )
+    })?;
+    Ok(ValidateArgs { policy, input })
+}
+
+pub fn parse_args(args: &[String]) -> Result<Config, ArgError> {
     let mut length = 16;
     let mut count = 1;
     let mut rounds = 8;
@@ -23,101 +468,358 @@ pub fn parse_args(args: &[String]) -> Config {
     let mut word_mode = false;
     let mut out_file = None;
     let mut format = OutputFormat::Plain;
+    let mut delimiter = None;
+    let mut name_template = None;
+    let mut username = None;
+    let mut hash = None;
+    let mut hash_only = false;
+    let mut hibp = None;
+    let mut charset = None;
+    let mut no_ambiguous = false;
+    let mut policy = Policy::default();
+    let mut require = None;
+    let mut pattern = None;
+    let mut target_bits = None;
+    let mut wordlist = None;
+    let mut phrase = PhraseStyle::default();
+    let mut phrase_set = false;
+    let mut seed = None;
+    let mut ordered = None;
+    let mut clear_after = None;
+    let mut length_flag = None;
+    let mut count_flag = None;
     let mut nums = Vec::new();
-    let mut i = 1;
+    // Флаги, заданные в командной строке, а не в файле настроек или окружении
+    let mut cli = std::collections::HashSet::new();
+    let mut in_config = args.iter().any(|a| a == CONFIG_END);
+    let mut p = Parser::new(args, 1);
 
-    while i < args.len() {
-        match args[i].as_str() {
+    while let Some(arg) = p.next()? {
+        let flag = match arg {
+            Arg::Positional(value) if in_config && value == CONFIG_END => {
+                in_config = false;
+                continue;
+            }
+            Arg::Flag(flag) => flag,
+            Arg::Positional(value) => {
+                nums.push(value.parse::<u64>().map_err(|_| ArgError::NotANumber(value))?);
+                continue;
+            }
+        };
+        if !in_config {
+            cli.insert(long_name(&flag).to_string());
+        }
+        match flag.as_str() {
             "-s" | "--stats" => show_stats = true,
+            "--no-stats" => show_stats = false,
             "-f" | "--fast" => fast_mode = true,
+            "--no-fast" => fast_mode = false,
             "-c" | "--copy" => copy_mode = true,
-            "-w" | "--words" => {
-                word_mode = true;
-                if length == 16 {
-                    length = 4;
-                } // Дефолт для фраз — 4 слова
+            "--no-copy" => copy_mode = false,
+            "--no-ambiguous" => no_ambiguous = true,
+            "--ambiguous" => no_ambiguous = false,
+            "--ordered" => ordered = Some(true),
+            "--unordered" => ordered = Some(false),
+            "--append-digit" => {
+                phrase.append_digit = true;
+                phrase_set = true;
+            }
+            "--no-append-digit" => phrase.append_digit = false,
+            "--append-symbol" => {
+                phrase.append_symbol = true;
+                phrase_set = true;
             }
+            "--no-append-symbol" => phrase.append_symbol = false,
+            "-w" | "--words" => word_mode = true,
+            "--no-words" => word_mode = false,
+            "--plain" => format = OutputFormat::Plain,
             "--json" => format = OutputFormat::Json,
+            "--json-objects" => format = OutputFormat::JsonObjects,
+            "--ndjson" => format = OutputFormat::Ndjson,
             "--csv" => format = OutputFormat::Csv,
-            "-o" => {
-                if i + 1 < args.len() {
-                    out_file = Some(args[i + 1].clone());
-                    i += 1;
+            "--keepass-csv" => format = OutputFormat::KeepassCsv,
+            "--keepass-xml" => format = OutputFormat::KeepassXml,
+            "--bitwarden" => format = OutputFormat::Bitwarden,
+            "--1password" => format = OutputFormat::OnePasswordCsv,
+            "--name-template" | "--username" => {
+                let value = Some(p.value(
+                    "значения",
+                    Some("passwg 20 100 --bitwarden --name-template 'svc-{id}' --username admin"),
+                )?);
+                if flag == "--username" {
+                    username = value;
                 } else {
-                    eprintln!("Ошибка: флаг -o требует указания имени файла");
-                    eprintln!("Пример: passwg -o passwords.txt");
-                    std::process::exit(1);
+                    name_template = value;
                 }
             }
-            "-h" | "--help" => {
-                // help уже обработан в main, но на всякий случай
-                std::process::exit(0);
-            }
-            "-r" | "--rounds" => {
-                if i + 1 < args.len() {
-                    match args[i + 1].parse::<u8>() {
-                        Ok(r) if r == 8 || r == 12 || r == 20 => {
-                            rounds = r;
-                            i += 1;
-                        }
-                        _ => {
-                            eprintln!(
-                                "Ошибка: неверное количество раундов. Допустимо только: 8, 12, 20"
-                            );
-                            std::process::exit(1);
-                        }
-                    }
-                } else {
-                    eprintln!("Ошибка: флаг -r требует указания числа (8, 12, 20)");
-                    std::process::exit(1);
+            "--hash-only" => hash_only = true,
+            "--no-hash-only" => hash_only = false,
+            "--hibp" => hibp = Some(p.value("пути к файлу Pwned Passwords", None)?),
+            "--hash" => {
+                let algo = p.value("алгоритма", Some("passwg 20 --hash sha512crypt"))?;
+                hash = Some(HashAlgo::parse(&algo).map_err(ArgError::Invalid)?);
+            }
+            "--tsv" => {
+                format = OutputFormat::Csv;
+                delimiter = Some(b'\t');
+            }
+            "--delimiter" => {
+                let expected = "один ASCII-символ, кроме кавычки и перевода строки";
+                match p.value(expected, None)?.as_bytes() {
+                    &[d] if d.is_ascii() && !matches!(d, b'"' | b'\r' | b'\n') => delimiter = Some(d),
+                    _ => return Err(ArgError::Invalid(format!("флаг --delimiter требует {}", expected))),
                 }
             }
-            arg if arg.starts_with('-') => {
-                // Неизвестный флаг
-                eprintln!("Ошибка: неизвестный флаг '{}'", arg);
-                eprintln!("Используйте -h для просмотра доступных флагов");
-                std::process::exit(1);
+            "-o" | "--output" => {
+                out_file = Some(p.value("указания имени файла", Some("passwg -o passwords.txt"))?);
             }
-            _ => {
-                if let Ok(n) = args[i].parse::<u64>() {
-                    nums.push(n);
-                } else {
-                    eprintln!("Ошибка: неверный аргумент '{}'", args[i]);
-                    eprintln!("Аргументы должны быть числами или флагами");
-                    eprintln!("Пример: passwg 20 5 -s");
-                    std::process::exit(1);
+            "--charset" => {
+                let spec = p.value("описания алфавита", Some("passwg --charset 'a-zA-Z0-9@#%'"))?;
+                charset = Some(Charset::parse(&spec).map_err(ArgError::Invalid)?);
+            }
+            "--require" => {
+                let spec = p.value(
+                    "описания политики",
+                    Some("passwg --require upper=2,digit=1,symbol=1"),
+                )?;
+                require = Some(Policy::parse(&spec).map_err(ArgError::Invalid)?);
+            }
+            "--policy" => {
+                let path = p.value("пути к файлу политики", None)?;
+                policy = Policy::load(&path).map_err(ArgError::Invalid)?;
+            }
+            "--pattern" => {
+                let spec = p.value("шаблона", Some("passwg --pattern '?u?l?l?l-?d?d?d?d-?s'"))?;
+                pattern = Some(Pattern::parse(&spec).map_err(ArgError::Invalid)?);
+            }
+            "--bits" => {
+                let message = "неверное значение --bits. Допустимо от 1 до 4096";
+                let bits: u32 = p.number("указания числа", message)?;
+                if bits > 4096 {
+                    return Err(ArgError::Invalid(message.into()));
                 }
+                target_bits = Some(bits as f64);
+            }
+            "--wordlist" => {
+                let name = p.value(
+                    "имени словаря или файла",
+                    Some("passwg -w --wordlist eff_large_wordlist.txt"),
+                )?;
+                wordlist = Some(Wordlist::open(&name).map_err(ArgError::Invalid)?);
+            }
+            "--sep" => {
+                phrase.sep = Separator::parse(&p.value("разделителя или random", Some("passwg -w --sep ' '"))?);
+                phrase_set = true;
+            }
+            "--case" => {
+                let case = p.value("значения (lower, title, upper, random)", None)?;
+                phrase.case = Case::parse(&case).map_err(ArgError::Invalid)?;
+                phrase_set = true;
+            }
+            "--seed" => {
+                let hex = p.value("hex-строки до 64 символов", Some("passwg --seed 00c0ffee"))?;
+                seed = Some(parse_seed(&hex).map_err(ArgError::Invalid)?);
+            }
+            "-h" | "--help" => return Err(ArgError::Help),
+            "--clear-after" => {
+                clear_after = Some(p.number(
+                    "числа секунд",
+                    "флаг --clear-after требует число секунд больше 0",
+                )?);
             }
+            "--length" => {
+                length_flag = Some(p.number("числа", "флаг --length требует число больше 0")?);
+            }
+            "--count" => {
+                count_flag = Some(p.number("числа", "флаг --count требует число больше 0")?);
+            }
+            "-r" | "--rounds" => match p.value("указания числа (8, 12, 20)", None)?.parse::<u8>() {
+                Ok(r) if r == 8 || r == 12 || r == 20 => rounds = r,
+                _ => {
+                    return Err(ArgError::Invalid(
+                        "неверное количество раундов. Допустимо только: 8, 12, 20".into(),
+                    ));
+                }
+            },
+            _ => return Err(ArgError::UnknownFlag(flag)),
         }
-        i += 1;
     }
 
-    // Проверяем конфликт флагов
+    if word_mode {
+        length = 4; // Дефолт для фраз — 4 слова
+    }
+
+    // Проверяем конфликт флагов. Умолчания из файла настроек, которые не подходят
+    // к выбранному режиму, молча не действуют; предупреждаем о флагах из командной строки
+    let given = |flags: &[&str]| flags.iter().any(|f| cli.contains(*f));
     if copy_mode && out_file.is_some() {
-        eprintln!("Предупреждение: флаг -c (копирование) игнорируется при использовании -o (файл)");
+        if given(&["--copy"]) {
+            eprintln!("Предупреждение: флаг -c (копирование) игнорируется при использовании -o (файл)");
+        }
         copy_mode = false;
     }
 
-    if fast_mode && word_mode {
+    if delimiter.is_some() && format != OutputFormat::Csv && given(&["--delimiter"]) {
+        eprintln!("Предупреждение: флаг --delimiter действует только вместе с --csv или --tsv");
+    }
+
+    let import_format = matches!(
+        format,
+        OutputFormat::KeepassCsv
+            | OutputFormat::KeepassXml
+            | OutputFormat::Bitwarden
+            | OutputFormat::OnePasswordCsv
+    );
+    if hash_only && hash.is_none() && given(&["--hash-only"]) {
+        eprintln!("Предупреждение: флаг --hash-only действует только вместе с --hash");
+    }
+
+    // --require уточняет минимумы по классам поверх файла политики
+    if let Some(r) = require {
+        policy.min = r.min;
+    }
+
+    let htpasswd = hash == Some(HashAlgo::Htpasswd);
+    if (name_template.is_some() || username.is_some())
+        && !import_format
+        && !htpasswd
+        && given(&["--name-template", "--username"])
+    {
+        eprintln!(
+            "Предупреждение: флаги --name-template и --username действуют только с --keepass-csv, --keepass-xml, --bitwarden и --1password"
+        );
+    }
+
+    if clear_after.is_some() && !copy_mode && given(&["--clear-after"]) {
+        eprintln!("Предупреждение: флаг --clear-after действует только вместе с -c (копирование)");
+    }
+
+    if fast_mode && word_mode && given(&["--fast"]) {
         eprintln!(
             "Предупреждение: флаг -f (быстрый режим) игнорируется при использовании -w (слова)"
         );
     }
 
-    if let Some(&l) = nums.get(0) {
+    if fast_mode && charset.is_some() {
+        if given(&["--fast"]) {
+            eprintln!(
+                "Предупреждение: флаг -f (быстрый режим) игнорируется при использовании --charset"
+            );
+        }
+        fast_mode = false;
+    }
+
+    if wordlist.is_some() && !word_mode && given(&["--wordlist"]) {
+        eprintln!("Предупреждение: флаг --wordlist действует только вместе с -w (слова)");
+    }
+
+    // CSV и JSON по умолчанию упорядочены: потребители ждут id 1..N подряд
+    let ordered = match ordered {
+        Some(false) if format.is_json_array() => {
+            if given(&["--unordered"]) {
+                eprintln!("Предупреждение: флаг --unordered игнорируется для JSON-массива, иначе массив будет некорректным");
+            }
+            true
+        }
+        Some(o) => o,
+        None => format != OutputFormat::Plain,
+    };
+
+    if seed.is_some() {
+        eprintln!(
+            "Предупреждение: --seed делает вывод воспроизводимым. НЕ используйте эти пароли как настоящие секреты"
+        );
+    }
+
+    if phrase_set && !word_mode && given(&["--sep", "--case", "--append-digit", "--append-symbol"]) {
+        eprintln!(
+            "Предупреждение: флаги --sep, --case, --append-digit и --append-symbol действуют только вместе с -w (слова)"
+        );
+    }
+
+    if let Some(p) = &pattern {
+        if word_mode || fast_mode || charset.is_some() {
+            if given(&["--words", "--fast", "--charset"]) {
+                eprintln!(
+                    "Предупреждение: флаги -w, -f и --charset игнорируются при использовании --pattern"
+                );
+            }
+            word_mode = false;
+            fast_mode = false;
+        }
+        if !policy.is_empty() {
+            return Err(ArgError::Invalid(
+                "флаги --require и --policy не поддерживаются вместе с --pattern".into(),
+            ));
+        }
+        if nums.len() > 1 {
+            return Err(ArgError::Invalid(
+                "длину задает шаблон, с --pattern указывается только количество паролей\nПример: passwg --pattern '?u?l?l?l-?d?d?d?d' 10".into(),
+            ));
+        }
+        if !nums.is_empty() {
+            // Единственное число — количество паролей, длину задает шаблон
+            nums.insert(0, p.len() as u64);
+        }
+        length = p.len();
+    }
+
+    if target_bits.is_some() {
+        if pattern.is_some() {
+            if given(&["--bits"]) {
+                eprintln!("Предупреждение: флаг --bits игнорируется при использовании --pattern");
+            }
+            target_bits = None;
+        } else if !nums.is_empty() {
+            // Длину подберем по энтропии, первое число — количество паролей
+            nums.insert(0, length as u64);
+        }
+    }
+
+    if no_ambiguous {
+        if let Some(p) = pattern.take() {
+            pattern = Some(p.without(AMBIGUOUS).map_err(ArgError::Invalid)?);
+        } else if word_mode {
+            if given(&["--no-ambiguous"]) {
+                eprintln!("Предупреждение: флаг --no-ambiguous игнорируется при использовании -w (слова)");
+            }
+        } else {
+            // Быстрый режим опирается на алфавит ровно из 64 символов,
+            // поэтому урезанный CHARSET_FAST генерируется через отсеивание
+            let base = match charset.take() {
+                Some(c) => c,
+                None if fast_mode => Charset::new(CHARSET_FAST.to_vec()).map_err(ArgError::Invalid)?,
+                None => Charset::default(),
+            };
+            charset = Some(base.without(AMBIGUOUS).map_err(ArgError::Invalid)?);
+            fast_mode = false;
+        }
+    }
+
+    if let Some(&l) = nums.first() {
         if l == 0 {
-            eprintln!("Ошибка: длина не может быть 0");
-            std::process::exit(1);
+            return Err(ArgError::Invalid("длина не может быть 0".into()));
         }
         length = l as usize;
+    } else if let Some(l) = length_flag.filter(|_| !word_mode || given(&["--length"])) {
+        // --length (обычно из профиля) слабее позиционного числа. Длина из файла
+        // настроек задана в символах, поэтому к фразе (-w) она не применяется
+        length = l;
+    } else if !word_mode && target_bits.is_none() && pattern.is_none() {
+        // Длина по умолчанию подстраивается под границы политики
+        length = length.max(policy.min_length);
+        if let Some(max) = policy.max_length {
+            length = length.min(max);
+        }
     }
 
     if let Some(&c) = nums.get(1) {
         if c == 0 {
-            eprintln!("Ошибка: количество не может быть 0");
-            std::process::exit(1);
+            return Err(ArgError::Invalid("количество не может быть 0".into()));
         }
         count = c;
+    } else if let Some(c) = count_flag {
+        count = c;
     }
 
     // Если включен режим слов, проверяем длину
@@ -129,7 +831,9 @@ pub fn parse_args(args: &[String]) -> Config {
         eprintln!("Рекомендуется не более 10 слов для удобства");
     }
 
-    Config {
+    let charset = charset.unwrap_or_default();
+
+    Ok(Config {
         length: if length == 0 { 1 } else { length },
         count,
         rounds,
@@ -139,7 +843,37 @@ pub fn parse_args(args: &[String]) -> Config {
         word_mode,
         out_file,
         format,
+        delimiter: delimiter.unwrap_or(b','),
+        name_template,
+        username,
+        hash,
+        hash_only,
+        hibp,
+        charset,
+        policy,
+        pattern,
+        target_bits,
+        wordlist: wordlist.unwrap_or_default(),
+        phrase,
+        seed,
+        ordered,
+        clear_after,
+    })
+}
+
+/// Hex-строка до 32 байт. Ключ генератора — SHA-256 от байтов сида: при
+/// дополнении нулями `--seed 00` и `--seed 0000` давали бы один и тот же вывод
+fn parse_seed(hex: &str) -> Result<[u8; 32], String> {
+    use sha2::{Digest, Sha256};
+    if hex.is_empty() || hex.len() > 64 || !hex.len().is_multiple_of(2) {
+        return Err("сид должен быть hex-строкой четной длины до 64 символов".into());
     }
+    let mut bytes = Vec::with_capacity(hex.len() / 2);
+    for pair in hex.as_bytes().chunks(2) {
+        let pair = std::str::from_utf8(pair).map_err(|_| "сид должен быть hex-строкой")?;
+        bytes.push(u8::from_str_radix(pair, 16).map_err(|_| format!("неверный hex в сиде: '{}'", pair))?);
+    }
+    Ok(Sha256::digest(&bytes).into())
 }
 
 pub fn print_help(l: &I18n, app_name: &str, version: &str) {
@@ -151,7 +885,15 @@ pub fn print_help(l: &I18n, app_name: &str, version: &str) {
     println!("\n{}", l.help_flags);
     println!("{}", l.help_out);
     println!("{}", l.help_json);
+    println!("{}", l.help_json_objects);
+    println!("{}", l.help_ndjson);
     println!("{}", l.help_csv);
+    println!("{}", l.help_tsv);
+    println!("{}", l.help_delimiter);
+    println!("{}", l.help_import);
+    println!("{}", l.help_name_template);
+    println!("{}", l.help_hash);
+    println!("{}", l.help_hibp);
     println!(
         "  -w, --words    {}",
         if l.help_usage.contains("Использование") {
@@ -164,5 +906,286 @@ pub fn print_help(l: &I18n, app_name: &str, version: &str) {
     println!("{}", l.help_fast);
     println!("{}", l.help_copy);
     println!("{}", l.help_rounds);
+    println!("{}", l.help_length_count);
+    println!("{}", l.help_profile);
+    println!("{}", l.help_charset);
+    println!("{}", l.help_no_ambiguous);
+    println!("{}", l.help_require);
+    println!("{}", l.help_policy);
+    println!("{}", l.help_pattern);
+    println!("{}", l.help_bits);
+    println!("{}", l.help_wordlist);
+    println!("{}", l.help_sep);
+    println!("{}", l.help_case);
+    println!("{}", l.help_append);
+    println!("{}", l.help_seed);
+    println!("{}", l.help_ordered);
+    println!("{}", l.help_clear_after);
     println!("{}", l.help_h);
+    println!("\n{}", l.help_syntax);
+    println!("\n{}", l.help_derive);
+    println!("\n{}", l.help_check);
+    println!("\n{}", l.help_validate);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        std::iter::once("passwg").chain(args.iter().copied()).map(String::from).collect()
+    }
+
+    fn parse(args: &[&str]) -> Config {
+        match parse_args(&argv(args)) {
+            Ok(config) => config,
+            Err(e) => panic!("{:?}: {}", args, e),
+        }
+    }
+
+    fn error(args: &[&str]) -> ArgError {
+        match parse_args(&argv(args)) {
+            Ok(_) => panic!("{:?} должны дать ошибку", args),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn parser_splits_clusters_and_attached_values() {
+        let args = argv(&["-sr20", "--rounds=12", "-ofile", "--", "-x"]);
+        let mut p = Parser::new(&args, 1);
+        let mut seen = Vec::new();
+        while let Some(arg) = p.next().unwrap() {
+            match arg {
+                Arg::Flag(flag) if flag == "-r" || flag == "--rounds" || flag == "-o" => {
+                    seen.push(format!("{}={}", flag, p.value("", None).unwrap()))
+                }
+                Arg::Flag(flag) => seen.push(flag),
+                Arg::Positional(value) => seen.push(format!("[{}]", value)),
+            }
+        }
+        assert_eq!(seen, ["-s", "-r=20", "--rounds=12", "-o=file", "[-x]"]);
+    }
+
+    #[test]
+    fn gnu_style_flags() {
+        assert_eq!(parse(&["--rounds=20"]).rounds, 20);
+        assert_eq!(parse(&["--rounds", "12"]).rounds, 12);
+        assert_eq!(parse(&["-r20"]).rounds, 20);
+
+        let config = parse(&["-sf"]);
+        assert!(config.show_stats && config.fast_mode);
+
+        assert_eq!(parse(&["-ofile"]).out_file.as_deref(), Some("file"));
+        assert_eq!(parse(&["--output=a=b"]).out_file.as_deref(), Some("a=b"));
+        let config = parse(&["-so", "file"]);
+        assert!(config.show_stats);
+        assert_eq!(config.out_file.as_deref(), Some("file"));
+
+        // Значение берется как есть, даже если похоже на флаг
+        assert_eq!(parse(&["--charset", "-_a"]).charset.symbols.len(), 3);
+
+        let config = parse(&["--", "20", "5"]);
+        assert_eq!((config.length, config.count), (20, 5));
+        assert_eq!(error(&["--", "-s"]), ArgError::NotANumber("-s".into()));
+        assert_eq!(parse(&[]).length, 16);
+    }
+
+    #[test]
+    fn flag_errors() {
+        assert_eq!(error(&["--stats=1"]), ArgError::UnexpectedValue("--stats".into()));
+        assert_eq!(error(&["-x"]), ArgError::UnknownFlag("-x".into()));
+        assert_eq!(error(&["-sx"]), ArgError::UnknownFlag("-x".into()));
+        assert_eq!(error(&["--lenght=10"]), ArgError::UnknownFlag("--lenght".into()));
+        assert_eq!(error(&["20", "-h"]), ArgError::Help);
+        assert_eq!(error(&["abc"]), ArgError::NotANumber("abc".into()));
+        assert!(matches!(error(&["-r16"]), ArgError::Invalid(_)));
+        assert!(matches!(error(&["--length=0"]), ArgError::Invalid(_)));
+    }
+
+    #[test]
+    fn missing_values() {
+        for (args, flag) in [
+            (&["--rounds"][..], "--rounds"),
+            (&["-r"], "-r"),
+            (&["-s", "-o"], "-o"),
+            (&["--hash"], "--hash"),
+            (&["--charset"], "--charset"),
+            (&["--seed"], "--seed"),
+        ] {
+            match error(args) {
+                ArgError::MissingValue { flag: f, .. } => assert_eq!(f, flag),
+                e => panic!("{:?}: {:?}", args, e),
+            }
+        }
+    }
+
+    #[test]
+    fn length_and_count_flags_are_weaker_than_positional() {
+        let config = parse(&["--length=30", "--count=4"]);
+        assert_eq!((config.length, config.count), (30, 4));
+        let config = parse(&["--length=30", "--count=4", "12"]);
+        assert_eq!((config.length, config.count), (12, 4));
+        let config = parse(&["--length=30", "--count=4", "12", "2"]);
+        assert_eq!((config.length, config.count), (12, 2));
+    }
+
+    #[test]
+    fn config_keys_are_known_flags() {
+        for (name, negation) in SWITCHES {
+            parse(&[&format!("--{}", name)]);
+            if let Some(negation) = negation {
+                parse(&[&format!("--{}", name), &format!("--{}", negation)]);
+            }
+        }
+        for name in OPTIONS {
+            // Значения неверные, но флаг должен быть известен
+            let flag = format!("--{}=x", name);
+            if let Err(ArgError::UnknownFlag(f)) = parse_args(&argv(&[&flag])) {
+                panic!("{} неизвестен", f);
+            }
+        }
+    }
+
+    #[test]
+    fn negations_override_switches() {
+        let config = parse(&["-sfc", "--no-stats", "--no-fast", "--no-copy"]);
+        assert!(!config.show_stats && !config.fast_mode && !config.copy_mode);
+        assert!(!parse(&["-w", "--no-words"]).word_mode);
+        assert_eq!(parse(&["-w", "--no-words"]).length, 16);
+        assert!(parse(&["--csv", "--plain"]).format == OutputFormat::Plain);
+        assert!(!parse(&["--hash=bcrypt", "--hash-only", "--no-hash-only"]).hash_only);
+    }
+
+    #[test]
+    fn pattern_takes_only_count() {
+        let config = parse(&["--pattern", "?u?l?d", "5"]);
+        assert_eq!((config.length, config.count), (3, 5));
+        assert_eq!(parse(&["--pattern", "?u?l?d"]).length, 3);
+        assert!(matches!(parse_args(&argv(&["--pattern", "?u?l", "2", "3"])), Err(ArgError::Invalid(_))));
+    }
+
+    #[test]
+    fn seeds_do_not_collide() {
+        let seed = |hex: &str| parse(&["--seed", hex]).seed.unwrap();
+        assert_ne!(seed("00"), seed("0000"));
+        assert_ne!(seed("c0ffee"), seed("c0ffee00"));
+        assert_eq!(seed("C0FFEE"), seed("c0ffee"));
+        for bad in ["0", "0g", &"00".repeat(33)] {
+            assert!(matches!(parse_args(&argv(&["--seed", bad])), Err(ArgError::Invalid(_))), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn derive_arguments() {
+        let args = argv(&["derive", "example.com", "--login=me", "--counter", "3", "20", "-s"]);
+        let Ok(derive) = parse_derive(&args) else { panic!("derive должен разобраться") };
+        assert_eq!((derive.site.as_str(), derive.login.as_str(), derive.counter), ("example.com", "me", 3));
+        assert_eq!(derive.config.length, 20);
+        assert!(derive.config.show_stats);
+
+        for rest in [&["-o", "out.txt"][..], &["20", "5"], &["--count=2"], &["--json"], &["--csv"], &["--hash", "bcrypt"]] {
+            let args: Vec<String> = argv(&["derive", "example.com"]).into_iter().chain(argv(rest).into_iter().skip(1)).collect();
+            assert!(matches!(parse_derive(&args), Err(ArgError::Invalid(_))), "{:?}", rest);
+        }
+
+        assert!(matches!(parse_derive(&argv(&["derive", "--login", "me"])), Err(ArgError::Invalid(_))));
+        assert!(matches!(
+            parse_derive(&argv(&["derive", "example.com", "--seed", "00"])),
+            Err(ArgError::Invalid(_))
+        ));
+        assert!(matches!(
+            parse_derive(&argv(&["derive", "example.com", "--hibp=db.txt"])),
+            Err(ArgError::Invalid(_))
+        ));
+        assert!(matches!(
+            parse_derive(&argv(&["derive", "example.com", "--counter"])),
+            Err(ArgError::MissingValue { .. })
+        ));
+    }
+
+    #[test]
+    fn check_and_validate_arguments() {
+        let check = parse_check(&argv(&["check", "--hibp=db.txt", "--csv", "input.txt"])).unwrap();
+        assert_eq!(check.hibp.as_deref(), Some("db.txt"));
+        assert_eq!(check.input.as_deref(), Some("input.txt"));
+        assert!(check.format == OutputFormat::Csv);
+        assert_eq!(
+            parse_check(&argv(&["check", "--csv=1"])).err(),
+            Some(ArgError::UnexpectedValue("--csv".into()))
+        );
+        assert!(matches!(parse_validate(&argv(&["validate"])), Err(ArgError::Invalid(_))));
+    }
+
+    /// Файл настроек, затем PASSWG_*, затем командная строка: последнее значение побеждает
+    #[test]
+    fn file_env_cli_precedence() {
+        let dir = std::env::temp_dir().join(format!("passwg-args-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("passwg")).unwrap();
+        std::fs::write(
+            dir.join("passwg/config.toml"),
+            "rounds = 12\ncount = 3\n\n[profile.long]\nlength = 40\nrounds = 20\n",
+        )
+        .unwrap();
+        // Единственный тест, который трогает окружение процесса
+        unsafe {
+            std::env::set_var("XDG_CONFIG_HOME", &dir);
+            std::env::remove_var("PASSWG_PROFILE");
+            std::env::remove_var("PASSWG_ROUNDS");
+        }
+        let run = |args: &[&str]| parse_args(&crate::profile::apply(&argv(args)).unwrap()).unwrap();
+
+        let config = run(&[]);
+        assert_eq!((config.rounds, config.count, config.length), (12, 3, 16));
+
+        let config = run(&["--profile", "long"]);
+        assert_eq!((config.rounds, config.length), (20, 40));
+        assert_eq!(run(&["--profile=long", "10"]).length, 10);
+
+        unsafe { std::env::set_var("PASSWG_ROUNDS", "8") };
+        assert_eq!(run(&[]).rounds, 8);
+        // Окружение сильнее профиля, командная строка сильнее окружения
+        assert_eq!(run(&["--profile", "long"]).rounds, 8);
+        assert_eq!(run(&["-r20"]).rounds, 20);
+        assert_eq!(run(&["--", "5"]).rounds, 8);
+
+        unsafe {
+            std::env::remove_var("PASSWG_ROUNDS");
+            std::env::set_var("PASSWG_PROFILE", "long");
+        }
+        assert_eq!(run(&[]).length, 40);
+        assert!(crate::profile::apply(&argv(&["--profile", "missing"])).is_err());
+        unsafe { std::env::remove_var("PASSWG_PROFILE") };
+
+        // Длина из файла — в символах: фраза остается из 4 слов, а флаг в командной строке действует
+        let config_file = dir.join("passwg/config.toml");
+        std::fs::write(&config_file, "length = 10\nfast = true\nsep = \"-\"\n\n[profile.slow]\nfast = false\n").unwrap();
+        let config = run(&[]);
+        assert_eq!(config.length, 10);
+        assert!(config.fast_mode);
+        let config = run(&["-w"]);
+        assert!(config.word_mode);
+        assert_eq!(config.length, 4);
+        assert_eq!(run(&["-w", "--length", "6"]).length, 6);
+        assert_eq!(run(&["-w", "5"]).length, 5);
+
+        // Булев ключ из файла отключается профилем или отрицанием в командной строке
+        assert!(!run(&["--profile", "slow"]).fast_mode);
+        assert!(!run(&["--no-fast"]).fast_mode);
+
+        std::fs::write(&config_file, "lenght = 10\n").unwrap();
+        let error = crate::profile::apply(&argv(&[])).unwrap_err();
+        assert!(error.contains("lenght") && error.contains(&config_file.display().to_string()), "{}", error);
+        std::fs::write(&config_file, "[profile.x]\nfast = \"yes\"\n").unwrap();
+        let error = crate::profile::apply(&argv(&["--profile=x"])).unwrap_err();
+        assert!(error.contains("[profile.x]") && error.contains("true или false"), "{}", error);
+        for text in ["seed = \"00ff\"\n", "[profile.x]\nseed = \"00ff\"\n"] {
+            std::fs::write(&config_file, text).unwrap();
+            let error = crate::profile::apply(&argv(&["--profile=x"])).unwrap_err();
+            assert!(error.contains("'seed'"), "{}", error);
+        }
+
+        unsafe { std::env::remove_var("XDG_CONFIG_HOME") };
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
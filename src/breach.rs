@@ -0,0 +1,252 @@
+use md4::Md4;
+use sha1::{Digest, Sha1};
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io;
+
+/// Чем захешированы пароли в файле Pwned Passwords
+#[derive(Clone, Copy, PartialEq)]
+pub enum BreachHash {
+    Sha1,
+    /// NTLM — MD4 от пароля в UTF-16LE
+    Ntlm,
+}
+
+/// Локальная копия базы Pwned Passwords (HIBP), отсортированная по хешу:
+/// строки `ХЕШ:ЧИСЛО`, как их выдает официальный загрузчик.
+/// Поиск — двоичный прямо по файлу, без загрузки в память и без сети.
+pub struct BreachDb {
+    file: File,
+    size: u64,
+    kind: BreachHash,
+}
+
+/// Строки базы короткие (40 hex + `:` + число), в такой буфер влезает две целиком
+const PROBE: usize = 256;
+
+impl BreachDb {
+    /// Открывает файл и определяет тип хеша по длине первой записи
+    pub fn open(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("не удалось открыть '{}': {}", path, e))?;
+        let size = file
+            .metadata()
+            .map_err(|e| format!("не удалось прочитать '{}': {}", path, e))?
+            .len();
+
+        let mut buf = [0u8; PROBE];
+        let n = read_at(&file, &mut buf, 0).map_err(|e| format!("ошибка чтения '{}': {}", path, e))?;
+        let first = buf[..n].split(|&b| b == b'\n').next().unwrap_or(&[]);
+        let hash_len = first.iter().position(|&b| b == b':').unwrap_or(first.len());
+        let kind = match hash_len {
+            40 => BreachHash::Sha1,
+            32 => BreachHash::Ntlm,
+            _ => {
+                return Err(format!(
+                    "'{}' не похож на файл Pwned Passwords: ожидаются строки ХЕШ:ЧИСЛО с SHA-1 или NTLM",
+                    path
+                ));
+            }
+        };
+        Ok(BreachDb { file, size, kind })
+    }
+
+    pub fn kind(&self) -> BreachHash {
+        self.kind
+    }
+
+    /// Сколько раз пароль встречался в утечках; `None`, если не встречался
+    pub fn lookup(&self, password: &[u8]) -> io::Result<Option<u64>> {
+        let target = self.hex_digest(password);
+
+        // Инвариант: искомая строка, если она есть, начинается в [lo, hi)
+        let mut lo = 0u64;
+        let mut hi = self.size;
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let Some((start, end, line)) = self.line_from(mid)? else {
+                hi = mid;
+                continue;
+            };
+            if start >= hi {
+                hi = mid;
+                continue;
+            }
+            let hash = &line[..line.len().min(target.len())];
+            match compare_hex(hash, &target) {
+                Ordering::Less => lo = end,
+                Ordering::Greater => hi = mid,
+                Ordering::Equal => {
+                    let count = line
+                        .get(target.len() + 1..)
+                        .and_then(|c| std::str::from_utf8(c).ok())
+                        .and_then(|c| c.trim().parse().ok())
+                        .unwrap_or(1);
+                    return Ok(Some(count));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn hex_digest(&self, password: &[u8]) -> Vec<u8> {
+        let digest = match self.kind {
+            BreachHash::Sha1 => Sha1::digest(password).to_vec(),
+            BreachHash::Ntlm => {
+                let mut utf16: Vec<u8> = String::from_utf8_lossy(password)
+                    .encode_utf16()
+                    .flat_map(|u| u.to_le_bytes())
+                    .collect();
+                let digest = Md4::digest(&utf16).to_vec();
+                zeroize::Zeroize::zeroize(&mut utf16);
+                digest
+            }
+        };
+        const HEX: &[u8] = b"0123456789ABCDEF";
+        digest
+            .iter()
+            .flat_map(|b| [HEX[(b >> 4) as usize], HEX[(b & 0xF) as usize]])
+            .collect()
+    }
+
+    /// Первая строка, начинающаяся не раньше `pos`: (начало, начало следующей, строка без \r\n)
+    fn line_from(&self, pos: u64) -> io::Result<Option<(u64, u64, Vec<u8>)>> {
+        // Читаем с байта перед pos, чтобы понять, начинается ли строка ровно в pos
+        let read_from = pos.saturating_sub(1);
+        let mut buf = [0u8; PROBE];
+        let n = read_at(&self.file, &mut buf, read_from)?;
+        let buf = &buf[..n];
+
+        let skip = if pos == 0 {
+            0
+        } else {
+            match buf.iter().position(|&b| b == b'\n') {
+                Some(i) => i + 1,
+                None => return Ok(None),
+            }
+        };
+        let start = read_from + skip as u64;
+        if start >= self.size {
+            return Ok(None);
+        }
+        let rest = &buf[skip..];
+        let (line, len) = match rest.iter().position(|&b| b == b'\n') {
+            Some(i) => (&rest[..i], i + 1),
+            // Последняя строка без перевода строки в конце файла
+            None => (rest, rest.len()),
+        };
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        Ok(Some((start, start + len as u64, line.to_vec())))
+    }
+}
+
+/// Сравнение hex без учета регистра: загрузчик пишет заглавными, но мало ли
+fn compare_hex(a: &[u8], b: &[u8]) -> Ordering {
+    a.iter()
+        .map(|c| c.to_ascii_uppercase())
+        .cmp(b.iter().map(|c| c.to_ascii_uppercase()))
+}
+
+/// Чтение по смещению без общего курсора: базу читают все потоки rayon сразу
+#[cfg(unix)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    use std::os::unix::fs::FileExt;
+    let mut total = 0;
+    while total < buf.len() {
+        match file.read_at(&mut buf[total..], offset + total as u64)? {
+            0 => break,
+            n => total += n,
+        }
+    }
+    Ok(total)
+}
+
+#[cfg(windows)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    use std::os::windows::fs::FileExt;
+    let mut total = 0;
+    while total < buf.len() {
+        match file.seek_read(&mut buf[total..], offset + total as u64)? {
+            0 => break,
+            n => total += n,
+        }
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hex-хеш, посчитанный независимо от `hex_digest`
+    fn digest(kind: BreachHash, password: &str) -> String {
+        let digest = match kind {
+            BreachHash::Sha1 => Sha1::digest(password).to_vec(),
+            BreachHash::Ntlm => {
+                let utf16: Vec<u8> = password.encode_utf16().flat_map(u16::to_le_bytes).collect();
+                Md4::digest(utf16).to_vec()
+            }
+        };
+        digest.iter().map(|b| format!("{:02X}", b)).collect()
+    }
+
+    /// Пишет отсортированную базу из паролей `pw0`..`pw49` с числом утечек `i + 1`
+    fn fixture(kind: BreachHash, newline: &str, tag: &str) -> (String, Vec<(String, String, u64)>) {
+        let mut entries: Vec<(String, String, u64)> = (0..50)
+            .map(|i| {
+                let password = format!("pw{}", i);
+                (digest(kind, &password), password, i + 1)
+            })
+            .collect();
+        entries.sort();
+        let text: String = entries.iter().map(|(h, _, n)| format!("{}:{}{}", h, n, newline)).collect();
+        let path = std::env::temp_dir().join(format!("passwg-breach-{}-{}.txt", tag, std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        (path.display().to_string(), entries)
+    }
+
+    fn check(kind: BreachHash, newline: &str, tag: &str) {
+        let (path, entries) = fixture(kind, newline, tag);
+        let db = BreachDb::open(&path).unwrap();
+        assert!(db.kind() == kind);
+        for (_, password, count) in &entries {
+            assert_eq!(db.lookup(password.as_bytes()).unwrap(), Some(*count), "{}", password);
+        }
+        // Первая и последняя строки файла — граничные случаи двоичного поиска
+        let (first, last) = (&entries[0], &entries[entries.len() - 1]);
+        assert_eq!(db.lookup(first.1.as_bytes()).unwrap(), Some(first.2));
+        assert_eq!(db.lookup(last.1.as_bytes()).unwrap(), Some(last.2));
+        for miss in ["pw50", "", "PW1", "correct horse battery staple"] {
+            assert_eq!(db.lookup(miss.as_bytes()).unwrap(), None, "{}", miss);
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn sha1_lookup() {
+        check(BreachHash::Sha1, "\n", "sha1");
+        check(BreachHash::Sha1, "\r\n", "sha1-crlf");
+    }
+
+    #[test]
+    fn ntlm_lookup() {
+        // Известный NTLM-хеш строки "password"
+        assert_eq!(digest(BreachHash::Ntlm, "password"), "8846F7EAEE8FB117AD06BDD830B7586C");
+        check(BreachHash::Ntlm, "\n", "ntlm");
+        check(BreachHash::Ntlm, "\r\n", "ntlm-crlf");
+    }
+
+    #[test]
+    fn last_line_without_newline_and_bad_files() {
+        let (path, entries) = fixture(BreachHash::Sha1, "\n", "tail");
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, text.trim_end()).unwrap();
+        let db = BreachDb::open(&path).unwrap();
+        let last = &entries[entries.len() - 1];
+        assert_eq!(db.lookup(last.1.as_bytes()).unwrap(), Some(last.2));
+
+        std::fs::write(&path, "not a hash:1\n").unwrap();
+        assert!(BreachDb::open(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+        assert!(BreachDb::open(&path).is_err());
+    }
+}
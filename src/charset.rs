@@ -0,0 +1,167 @@
+use crate::generator::CHARSET;
+
+pub const LOWER: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+pub const UPPER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+pub const DIGITS: &[u8] = b"0123456789";
+pub const SYMBOLS: &[u8] = b"!#$%&'()*+,-./:;<=>?@[]^_`{|}~";
+/// Символы, которые легко перепутать при чтении с экрана или бумаги
+pub const AMBIGUOUS: &[u8] = b"0Oo1lI|`'\"";
+
+/// Алфавит для генерации вместе с порогом отсеивания (Rejection Sampling).
+/// Порог считается один раз при создании, а не на каждый символ.
+#[derive(Clone)]
+pub struct Charset {
+    pub symbols: Vec<u8>,
+    pub limit: u32,
+}
+
+impl Charset {
+    /// Алфавит из готового набора байт: только печатные ASCII без пробела,
+    /// минимум 2 различных символа. Управляющие байты раздули бы экранирование
+    /// в JSON до `\u00XX`, а байты выше 0x7F — не UTF-8
+    pub fn new(symbols: Vec<u8>) -> Result<Self, String> {
+        if let Some(&b) = symbols.iter().find(|b| !(0x21..=0x7E).contains(*b)) {
+            return Err(format!(
+                "недопустимый байт 0x{:02X} в алфавите: разрешены только печатные ASCII без пробела",
+                b
+            ));
+        }
+        let mut unique = Vec::with_capacity(symbols.len());
+        push_unique(&mut unique, &symbols);
+        if unique.len() < 2 {
+            return Err("алфавит должен содержать минимум 2 различных символа".into());
+        }
+        Ok(Charset::known(unique))
+    }
+
+    /// Алфавит из заведомо допустимых символов, без проверок
+    pub(crate) fn known(symbols: Vec<u8>) -> Self {
+        let len = symbols.len() as u32;
+        // Наибольшее кратное длины алфавита, влезающее в u32
+        let limit = (u32::MAX / len) * len;
+        Charset { symbols, limit }
+    }
+
+    /// Разбирает описание алфавита вида `a-zA-Z0-9@#%` или `lower,digits,@#`.
+    /// Части разделяются запятой; часть может быть именем класса
+    /// (lower, upper, digits, symbols) или набором символов с диапазонами.
+    /// `\,` `\-` и `\\` задают литералы.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut symbols: Vec<u8> = Vec::new();
+
+        for part in split_unescaped(spec) {
+            match part.as_str() {
+                "lower" => push_unique(&mut symbols, LOWER),
+                "upper" => push_unique(&mut symbols, UPPER),
+                "digits" => push_unique(&mut symbols, DIGITS),
+                "symbols" => push_unique(&mut symbols, SYMBOLS),
+                "" => {}
+                _ => push_unique(&mut symbols, &expand_ranges(&part)?),
+            }
+        }
+
+        if symbols.len() < 2 {
+            return Err(format!(
+                "алфавит '{}' должен содержать минимум 2 различных символа",
+                spec
+            ));
+        }
+        Ok(Charset::known(symbols))
+    }
+
+    /// Возвращает алфавит без указанных символов. Порог отсеивания пересчитывается.
+    pub fn without(&self, excluded: &[u8]) -> Result<Self, String> {
+        let symbols: Vec<u8> = self
+            .symbols
+            .iter()
+            .copied()
+            .filter(|b| !excluded.contains(b))
+            .collect();
+        if symbols.len() < 2 {
+            return Err("после исключения символов в алфавите осталось меньше 2 символов".into());
+        }
+        Ok(Charset::known(symbols))
+    }
+}
+
+impl Default for Charset {
+    fn default() -> Self {
+        Charset::known(CHARSET.to_vec())
+    }
+}
+
+/// Делит описание по запятым, сохраняя экранирование для `expand_ranges`.
+fn split_unescaped(spec: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut chars = spec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                current.push('\\');
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            ',' => parts.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    parts.push(current);
+    parts
+}
+
+/// Раскрывает диапазоны `a-z`. Дефис в начале или конце — обычный символ.
+fn expand_ranges(part: &str) -> Result<Vec<u8>, String> {
+    // Сначала снимаем экранирование, запоминая какие символы были литералами
+    let mut items: Vec<(u8, bool)> = Vec::new();
+    let mut bytes = part.bytes();
+    while let Some(b) = bytes.next() {
+        if b == b'\\' {
+            match bytes.next() {
+                Some(next) => items.push((next, true)),
+                None => items.push((b'\\', true)),
+            }
+        } else {
+            items.push((b, false));
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < items.len() {
+        let (b, _) = items[i];
+        if !(0x21..=0x7E).contains(&b) {
+            return Err(format!(
+                "недопустимый символ в алфавите '{}': разрешены только печатные ASCII без пробела",
+                part
+            ));
+        }
+
+        let is_range = i + 2 < items.len() && items[i + 1] == (b'-', false);
+        if is_range {
+            let end = items[i + 2].0;
+            if end < b || !(0x21..=0x7E).contains(&end) {
+                return Err(format!(
+                    "неверный диапазон '{}-{}' в алфавите",
+                    b as char, end as char
+                ));
+            }
+            out.extend(b..=end);
+            i += 3;
+        } else {
+            out.push(b);
+            i += 1;
+        }
+    }
+    Ok(out)
+}
+
+fn push_unique(dst: &mut Vec<u8>, src: &[u8]) {
+    for &b in src {
+        if !dst.contains(&b) {
+            dst.push(b);
+        }
+    }
+}
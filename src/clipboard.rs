@@ -0,0 +1,130 @@
+use arboard::Clipboard;
+use passwg::Secret;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Скрытый аргумент для запуска фонового процесса, который держит буфер обмена
+pub const DAEMON_ARG: &str = "__clipboard-daemon";
+
+/// Копирует пароль в буфер обмена.
+/// В X11 и Wayland содержимое буфера живет, пока владелец отвечает на запросы,
+/// поэтому на Linux пароль передается фоновому процессу через pipe.
+/// Там же ждем `clear_after` секунд, чтобы не держать терминал.
+pub fn copy(pwd: &str, clear_after: Option<u64>) -> Result<(), String> {
+    // Проверяем доступность буфера заранее, чтобы ошибка была видна сразу
+    Clipboard::new().map_err(|e| e.to_string())?;
+
+    if cfg!(target_os = "linux") || clear_after.is_some() {
+        spawn_daemon(pwd, clear_after)
+    } else {
+        Clipboard::new()
+            .and_then(|mut c| c.set_text(pwd))
+            .map_err(|e| e.to_string())
+    }
+}
+
+fn spawn_daemon(pwd: &str, clear_after: Option<u64>) -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let mut cmd = Command::new(exe);
+    cmd.arg(DAEMON_ARG)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    if let Some(secs) = clear_after {
+        cmd.arg(secs.to_string());
+    }
+
+    let mut child = cmd.spawn().map_err(|e| e.to_string())?;
+    // Пароль не попадает в argv, где его видно через /proc
+    let mut stdin = child.stdin.take().ok_or("нет stdin у фонового процесса")?;
+    stdin.write_all(pwd.as_bytes()).map_err(|e| e.to_string())?;
+    drop(stdin);
+
+    // Фоновый процесс отвечает одной строкой: `ok` или текст ошибки
+    let stdout = child.stdout.take().ok_or("нет stdout у фонового процесса")?;
+    let mut status = String::new();
+    BufReader::new(stdout).read_line(&mut status).map_err(|e| e.to_string())?;
+    match status.trim_end() {
+        "ok" => Ok(()),
+        "" => Err("фоновый процесс буфера обмена завершился без ответа".into()),
+        error => Err(error.to_string()),
+    }
+}
+
+/// Точка входа фонового процесса: `passwg __clipboard-daemon [секунды]`.
+/// Сообщает родителю в stdout, удалось ли занять буфер, и держит его, пока
+/// буфер не перехватил кто-то другой или не истек таймер.
+pub fn run_daemon(raw_args: &[String]) -> ! {
+    let clear_after = raw_args.get(2).and_then(|s| s.parse::<u64>().ok());
+
+    let mut buf = String::new();
+    let read = std::io::stdin().read_to_string(&mut buf);
+    let pwd = Secret::from(buf);
+    if read.is_err() {
+        drop(pwd);
+        std::process::exit(1);
+    }
+
+    let mut clipboard = match Clipboard::new().and_then(|mut c| set_text(&mut c, pwd.expose()).map(|_| c)) {
+        Ok(clipboard) => clipboard,
+        Err(e) => {
+            // Родитель печатает ошибку и выходит с кодом 1
+            let mut out = std::io::stdout();
+            let _ = writeln!(out, "{}", e.to_string().replace('\n', " "));
+            let _ = out.flush();
+            drop(pwd);
+            std::process::exit(1);
+        }
+    };
+    let mut out = std::io::stdout();
+    let _ = writeln!(out, "ok").and_then(|_| out.flush());
+
+    hold(&mut clipboard, pwd.expose(), clear_after);
+    if clear_after.is_some() {
+        // Чистим только если там все еще наш пароль, а не то, что скопировал пользователь
+        let still_ours = clipboard
+            .get_text()
+            .map(|t| Secret::from(t) == pwd)
+            .unwrap_or(false);
+        if still_ours {
+            let _ = clipboard.clear();
+        }
+    }
+    // exit не вызывает деструкторы: затираем пароль явно
+    drop(pwd);
+    drop(clipboard);
+    std::process::exit(0);
+}
+
+#[cfg(target_os = "linux")]
+fn set_text(clipboard: &mut Clipboard, pwd: &str) -> Result<(), arboard::Error> {
+    use arboard::SetExtLinux;
+    // Менеджеры истории буфера не должны сохранять пароль
+    clipboard.set().exclude_from_history().text(pwd)
+}
+
+/// Держит буфер, пока его не перехватят или не истекут `clear_after` секунд
+#[cfg(target_os = "linux")]
+fn hold(clipboard: &mut Clipboard, pwd: &str, clear_after: Option<u64>) {
+    use arboard::SetExtLinux;
+    let set = clipboard.set().exclude_from_history();
+    let _ = match clear_after {
+        Some(secs) => set
+            .wait_until(std::time::Instant::now() + Duration::from_secs(secs))
+            .text(pwd),
+        None => set.wait().text(pwd),
+    };
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_text(clipboard: &mut Clipboard, pwd: &str) -> Result<(), arboard::Error> {
+    clipboard.set_text(pwd)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn hold(_clipboard: &mut Clipboard, _pwd: &str, clear_after: Option<u64>) {
+    if let Some(secs) = clear_after {
+        std::thread::sleep(Duration::from_secs(secs));
+    }
+}
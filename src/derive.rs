@@ -0,0 +1,38 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+
+/// Память Argon2id в КиБ (64 МиБ), число проходов и потоков.
+/// Менять нельзя: от них зависят все ранее выведенные пароли.
+const MEMORY_KIB: u32 = 64 * 1024;
+const ITERATIONS: u32 = 3;
+const LANES: u32 = 1;
+
+/// Выводит сид генератора из мастер-пароля в стиле LessPass/Spectre.
+/// Один и тот же набор (мастер, сайт, логин, счетчик) на любой машине дает
+/// один и тот же сид, а значит и пароль. Счетчик меняют, когда пароль
+/// на сайте нужно сменить, не меняя мастер.
+pub fn derive_seed(master: &[u8], site: &str, login: &str, counter: u32) -> Result<[u8; 32], String> {
+    if master.is_empty() {
+        return Err("мастер-пароль не может быть пустым".into());
+    }
+    let site = site.trim().to_lowercase();
+    if site.is_empty() {
+        return Err("имя сайта не может быть пустым".into());
+    }
+
+    // Соль: домен версии + поля через нулевой байт, чтобы ("ab", "c") != ("a", "bc")
+    let mut salt = Vec::new();
+    salt.extend_from_slice(b"passwg.derive.v1\0");
+    salt.extend_from_slice(site.as_bytes());
+    salt.push(0);
+    salt.extend_from_slice(login.as_bytes());
+    salt.push(0);
+    salt.extend_from_slice(&counter.to_be_bytes());
+
+    let params = Params::new(MEMORY_KIB, ITERATIONS, LANES, Some(32))
+        .map_err(|e| format!("неверные параметры Argon2: {}", e))?;
+    let mut seed = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(master, &salt, &mut seed)
+        .map_err(|e| format!("ошибка Argon2: {}", e))?;
+    Ok(seed)
+}
@@ -1,82 +1,736 @@
 #![allow(dead_code)]
-use crate::i18n::I18n;
-use crate::words::WORDLIST;
+use crate::breach::BreachDb;
+use crate::charset::Charset;
+use crate::hash::HashAlgo;
+use crate::import::{Layout, Piece};
+use crate::pattern::{Pattern, Slot};
+use crate::phrase::{APPEND_SYMBOLS, PhraseStyle, RANDOM_SEPARATORS, Separator};
+use crate::policy::{MIN_ACCEPTANCE, Policy};
+use crate::secret::{Secret, SecretBuf};
+use crate::words::Wordlist;
 use crate::writer::OutputFormat;
 // Импортируем все варианты ChaCha
 use rand_chacha::{ChaCha8Rng, ChaCha12Rng, ChaCha20Rng};
 use rand_core::{RngCore, SeedableRng};
-use std::time::Instant;
+use zeroize::Zeroize;
 
 pub const CHARSET: &[u8] =
     b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz!#$%&'()*+,-./:;<=>?@[]^_`{|}~";
 pub const CHARSET_LEN: usize = 92;
 pub const CHARSET_FAST: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_-";
+/// С --seed каждые SEED_BLOCK паролей берутся из своего потока. Чанки, кратные
+/// блоку, начинаются с начала потока и не генерируют лишнего
+pub const SEED_BLOCK: u64 = 8;
 
-const CHARSET_LIMIT: u32 = (u32::MAX / CHARSET_LEN as u32) * CHARSET_LEN as u32;
+/// Предел для `--bits`: 4096 бит из алфавита в 2 символа
+const MAX_FIT_LENGTH: usize = 8192;
 
-/// Публичная точка входа. Выбирает алгоритм на основе rounds и вызывает generic-функцию.
-pub fn generate_chunk(
-    start_id: u64,
-    size: u64,
+/// Параметры генерации, общие для всех чанков.
+pub struct GenParams {
+    pub length: usize,
+    pub fast_mode: bool,
+    pub word_mode: bool,
+    pub format: OutputFormat,
+    /// Разделитель полей CSV (`,`, `;`, `\t`)
+    pub delimiter: u8,
+    /// Название записи для форматов менеджеров паролей, `{id}` — номер пароля
+    pub name_template: String,
+    /// Имя пользователя для форматов менеджеров паролей, тоже с `{id}`
+    pub username: String,
+    /// Хеш, который пишется рядом с паролем или вместо него
+    pub hash: Option<HashAlgo>,
+    /// Выводить только хеш, без открытого пароля
+    pub hash_only: bool,
+    /// Локальная база утечек: найденные в ней пароли генерируются заново
+    pub breach: Option<BreachDb>,
+    pub rounds: u8,
+    pub charset: Charset,
+    pub policy: Policy,
+    pub pattern: Option<Pattern>,
+    pub wordlist: Wordlist,
+    pub phrase: PhraseStyle,
+    /// Словарь в нужном регистре; для `--case random` — три варианта
+    pub cased: Vec<Wordlist>,
+    /// Мастер-сид для воспроизводимого вывода. НЕ для настоящих паролей
+    pub seed: Option<[u8; 32]>,
+}
+
+impl GenParams {
+    /// Энтропия одного пароля в битах для текущего режима
+    pub fn entropy_bits(&self) -> f64 {
+        self.entropy_bits_at(self.length)
+    }
+
+    fn entropy_bits_at(&self, length: usize) -> f64 {
+        if let Some(pattern) = &self.pattern {
+            return pattern.entropy_bits();
+        }
+        if self.word_mode {
+            return (self.wordlist.len() as f64).log2() * length as f64
+                + self.phrase.extra_bits(length);
+        }
+        let alphabet = self.alphabet();
+        let bits = (alphabet.len() as f64).log2() * length as f64;
+        // Отбраковка по политике уменьшает число возможных паролей
+        bits + self.policy.acceptance(length, alphabet).map_or(f64::NEG_INFINITY, f64::log2)
+    }
+
+    /// Минимальная длина (или число слов), дающая не меньше `bits` бит энтропии
+    pub fn fit_length(&self, bits: f64) -> Result<usize, String> {
+        let per_item = if self.word_mode {
+            (self.wordlist.len() as f64).log2()
+        } else {
+            // Класс, которого нет в алфавите, не появится ни при какой длине
+            self.policy.validate_alphabet(self.alphabet())?;
+            (self.alphabet().len() as f64).log2()
+        };
+        // Оформление фраз добавляет энтропию, поэтому слова считаем с единицы.
+        // Короче суммы минимумов политики пароль быть не может
+        let mut length = if self.word_mode {
+            1
+        } else {
+            let required = self.policy.min.iter().sum::<usize>().max(self.policy.min_length);
+            // Заодно убеждаемся, что политику вообще можно посчитать
+            self.policy.acceptance(required, self.alphabet())?;
+            ((bits / per_item).ceil() as usize).max(required).max(1)
+        };
+        // Политика отнимает часть энтропии и может отбраковывать почти все
+        // короткие пароли, поэтому добираем длину по одному символу
+        let enough = |length: usize| {
+            self.entropy_bits_at(length) >= bits
+                && self
+                    .policy
+                    .acceptance(length, self.alphabet())
+                    .is_ok_and(|a| a >= MIN_ACCEPTANCE)
+        };
+        while length <= MAX_FIT_LENGTH && !enough(length) {
+            length += 1;
+        }
+        if length > MAX_FIT_LENGTH {
+            return Err(format!("не удалось подобрать длину для {} бит энтропии", bits));
+        }
+        Ok(length)
+    }
+
+    /// Верхняя граница длины одного пароля в байтах.
+    /// В режиме слов length — это число слов, поэтому берем худший случай по длине слова
+    pub fn max_password_len(&self) -> usize {
+        if self.word_mode && self.pattern.is_none() {
+            let longest = self.cased.iter().map(|w| w.longest()).max().unwrap_or(0);
+            self.length * (longest + self.phrase.sep.max_len()) + 2
+        } else if let Some(pattern) = &self.pattern {
+            pattern.len()
+        } else {
+            self.length
+        }
+    }
+
+    /// Верхняя граница длины одной записи в байтах с учетом формата и экранирования
+    pub fn max_record_len(&self) -> usize {
+        let password = self.max_password_len();
+        // Хеш, разделитель и имя пользователя htpasswd с номером
+        let hash = match self.hash {
+            Some(algo) => algo.max_len() + 2 * self.username.len().max(self.name_template.len()) + 24,
+            None => 0,
+        };
+        hash + match self.format {
+            // Пара `пароль,хеш`: пароль в кавычках CSV с удвоенными `"` и разделитель
+            OutputFormat::Plain if self.hash.is_some() && !self.hash_only => 2 * password + 2 + 1,
+            OutputFormat::Plain => password + 1,
+            // id до 20 цифр, разделитель, кавычки и удвоенные кавычки внутри
+            OutputFormat::Csv => 20 + 1 + 2 * password + 2 + 1,
+            // `,\n  "`, пароль и `"`. Управляющий символ превращается в \u00XX — до 6 байт на байт
+            OutputFormat::Json => 5 + 6 * password + 1,
+            // `,\n  {"id":` и `,"password":"` (23), id и длина до 20 цифр, `","length":` (11)
+            OutputFormat::JsonObjects | OutputFormat::Ndjson => {
+                23 + 20 + 6 * password + 11 + 20 + self.json_meta().len() + 1
+            }
+            // Худший случай экранирования: `&amp;` в XML, \u00XX в JSON
+            _ => {
+                let layout = self.import_layout().map(|l| l.max_len()).unwrap_or(0);
+                2 + layout + 6 * password
+            }
+        }
+    }
+
+    fn import_layout(&self) -> Option<Layout> {
+        crate::import::layout(self.format, &self.name_template, &self.username)
+    }
+
+    /// Название режима для метаданных `--json-objects` и `--ndjson`
+    pub fn mode_name(&self) -> &'static str {
+        if self.pattern.is_some() {
+            "pattern"
+        } else if self.word_mode {
+            "words"
+        } else if self.fast_mode {
+            "fast"
+        } else {
+            "chars"
+        }
+    }
+
+    /// Все символы, которые могут встретиться в пароле. Для фраз алфавита нет
+    pub fn charset_label(&self) -> Option<String> {
+        if let Some(pattern) = &self.pattern {
+            let mut symbols = Vec::new();
+            for slot in &pattern.slots {
+                match slot {
+                    Slot::Literal(b) => symbols.push(*b),
+                    Slot::Class(c) => symbols.extend_from_slice(&c.symbols),
+                }
+            }
+            symbols.sort_unstable();
+            symbols.dedup();
+            return Some(String::from_utf8_lossy(&symbols).into_owned());
+        }
+        if self.word_mode {
+            return None;
+        }
+        Some(String::from_utf8_lossy(self.alphabet()).into_owned())
+    }
+
+    /// Общая для всех паролей часть объекта `--json-objects` и `--ndjson` после поля length
+    fn json_meta(&self) -> Vec<u8> {
+        let charset = match self.charset_label() {
+            Some(c) => format!("\"{}\"", crate::writer::json_escape(&c)),
+            None => "null".into(),
+        };
+        format!(
+            ",\"entropy_bits\":{:.2},\"charset\":{},\"mode\":\"{}\"}}",
+            self.entropy_bits(),
+            charset,
+            self.mode_name()
+        )
+        .into_bytes()
+    }
+
+    /// Алфавит, из которого фактически берутся символы
+    pub fn alphabet(&self) -> &[u8] {
+        if self.fast_mode {
+            CHARSET_FAST
+        } else {
+            &self.charset.symbols
+        }
+    }
+}
+
+/// Режим генерации
+#[derive(Clone)]
+pub enum Mode {
+    /// Символы из алфавита (`Charset`) с отсеиванием
+    Chars,
+    /// Алфавит `CHARSET_FAST` из 64 символов через битовые маски и AVX2
+    Fast,
+    /// Фраза из слов; длина — число слов
+    Words,
+    /// Маска в стиле hashcat; длину задает сам шаблон
+    Pattern(Pattern),
+}
+
+/// Сборщик параметров генерации. Используется и CLI, и библиотекой.
+pub struct GeneratorBuilder {
     length: usize,
-    fast_mode: bool,
-    word_mode: bool,
+    mode: Mode,
+    rounds: u8,
+    charset: Charset,
+    policy: Policy,
+    wordlist: Wordlist,
+    phrase: PhraseStyle,
     format: OutputFormat,
-    rounds: u8, // Новый параметр
-) -> Vec<u8> {
-    let mut seed = [0u8; 32];
-    // Используем системную энтропию для инициализации
-    let _ = getrandom::fill(&mut seed);
-
-    match rounds {
-        12 => generate_internal(
-            ChaCha12Rng::from_seed(seed),
-            start_id,
-            size,
-            length,
-            fast_mode,
-            word_mode,
-            format,
-        ),
-        20 => generate_internal(
-            ChaCha20Rng::from_seed(seed),
-            start_id,
-            size,
-            length,
-            fast_mode,
-            word_mode,
-            format,
-        ),
-        _ => generate_internal(
-            ChaCha8Rng::from_seed(seed),
-            start_id,
-            size,
-            length,
+    delimiter: u8,
+    name_template: String,
+    username: String,
+    hash: Option<HashAlgo>,
+    hash_only: bool,
+    breach_db: Option<String>,
+    target_bits: Option<f64>,
+    seed: Option<[u8; 32]>,
+}
+
+impl Default for GeneratorBuilder {
+    fn default() -> Self {
+        GeneratorBuilder {
+            length: 16,
+            mode: Mode::Chars,
+            rounds: 8,
+            charset: Charset::default(),
+            policy: Policy::default(),
+            wordlist: Wordlist::default(),
+            phrase: PhraseStyle::default(),
+            format: OutputFormat::Plain,
+            delimiter: b',',
+            name_template: "passwg-{id}".into(),
+            username: String::new(),
+            hash: None,
+            hash_only: false,
+            breach_db: None,
+            target_bits: None,
+            seed: None,
+        }
+    }
+}
+
+impl GeneratorBuilder {
+    pub fn length(mut self, length: usize) -> Self {
+        self.length = length;
+        self
+    }
+
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Раунды ChaCha: 8, 12 или 20
+    pub fn rounds(mut self, rounds: u8) -> Self {
+        self.rounds = rounds;
+        self
+    }
+
+    pub fn charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    pub fn policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn wordlist(mut self, wordlist: Wordlist) -> Self {
+        self.wordlist = wordlist;
+        self
+    }
+
+    pub fn phrase(mut self, phrase: PhraseStyle) -> Self {
+        self.phrase = phrase;
+        self
+    }
+
+    /// Формат вывода для `generate_chunk`. На `Generator::generate` не влияет
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Разделитель полей для `OutputFormat::Csv`
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Название записи в форматах менеджеров паролей. `{id}` заменяется номером
+    pub fn name_template(mut self, template: impl Into<String>) -> Self {
+        self.name_template = template.into();
+        self
+    }
+
+    /// Имя пользователя в форматах менеджеров паролей. `{id}` заменяется номером
+    pub fn username(mut self, username: impl Into<String>) -> Self {
+        self.username = username.into();
+        self
+    }
+
+    /// Хешировать каждый пароль в `generate_chunk`. Хеш пишется через запятую
+    /// после пароля, а с `hash_only(true)` — вместо него
+    pub fn hash(mut self, algo: HashAlgo) -> Self {
+        self.hash = Some(algo);
+        self
+    }
+
+    pub fn hash_only(mut self, hash_only: bool) -> Self {
+        self.hash_only = hash_only;
+        self
+    }
+
+    /// Путь к отсортированному файлу Pwned Passwords (SHA-1 или NTLM).
+    /// Пароли из утечек отбрасываются и генерируются заново
+    pub fn breach_db(mut self, path: impl Into<String>) -> Self {
+        self.breach_db = Some(path.into());
+        self
+    }
+
+    /// Подобрать длину (или число слов) под заданную энтропию вместо `length`
+    pub fn target_bits(mut self, bits: f64) -> Self {
+        self.target_bits = Some(bits);
+        self
+    }
+
+    /// Детерминированный режим: одинаковый сид дает одинаковые пароли.
+    /// Только для тестов и фикстур — такие пароли нельзя считать секретными
+    pub fn seed(mut self, seed: [u8; 32]) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Проверяет настройки и собирает параметры для `generate_chunk`
+    pub fn build_params(self) -> Result<GenParams, String> {
+        if ![8, 12, 20].contains(&self.rounds) {
+            return Err("неверное количество раундов. Допустимо только: 8, 12, 20".into());
+        }
+        let (fast_mode, word_mode, pattern) = match self.mode {
+            Mode::Chars => (false, false, None),
+            Mode::Fast => (true, false, None),
+            Mode::Words => (false, true, None),
+            Mode::Pattern(p) => (false, false, Some(p)),
+        };
+        if matches!(self.delimiter, b'"' | b'\r' | b'\n') {
+            return Err("разделитель CSV не может быть кавычкой или переводом строки".into());
+        }
+        if let Some(algo) = self.hash {
+            let supported = match algo {
+                HashAlgo::Htpasswd => self.format == OutputFormat::Plain,
+                _ => matches!(self.format, OutputFormat::Plain | OutputFormat::Csv),
+            };
+            if !supported {
+                return Err(
+                    "--hash поддерживается только для обычного вывода и --csv, htpasswd — только для обычного".into(),
+                );
+            }
+        }
+        if !self.policy.is_empty() && (word_mode || pattern.is_some()) {
+            return Err("политика (--require, --policy) работает только с символьными паролями".into());
+        }
+
+        let mut params = GenParams {
+            length: self.length,
             fast_mode,
             word_mode,
-            format,
-        ),
+            format: self.format,
+            delimiter: self.delimiter,
+            name_template: self.name_template,
+            username: self.username,
+            // Файл htpasswd содержит только хеши
+            hash_only: self.hash_only || self.hash == Some(HashAlgo::Htpasswd),
+            hash: self.hash,
+            breach: self.breach_db.as_deref().map(BreachDb::open).transpose()?,
+            rounds: self.rounds,
+            charset: self.charset,
+            policy: self.policy,
+            cased: self.phrase.case.variants(&self.wordlist),
+            pattern,
+            wordlist: self.wordlist,
+            phrase: self.phrase,
+            seed: self.seed,
+        };
+
+        // Запрещенные политикой символы просто не попадают в алфавит
+        let forbidden = params.policy.forbidden_bytes();
+        if !word_mode && !forbidden.is_empty() {
+            if !fast_mode {
+                params.charset = params.charset.without(&forbidden)?;
+            } else if CHARSET_FAST.iter().any(|b| forbidden.contains(b)) {
+                return Err("политика запрещает символы из алфавита -f, используйте обычный режим".into());
+            }
+        }
+
+        if let Some(pattern) = &params.pattern {
+            params.length = pattern.len();
+        } else if let Some(bits) = self.target_bits {
+            params.length = params.fit_length(bits)?;
+        }
+        if params.length == 0 {
+            return Err("длина не может быть 0".into());
+        }
+        if !word_mode {
+            params.policy.validate(params.length, params.alphabet())?;
+        }
+        Ok(params)
+    }
+
+    /// Собирает генератор, засеянный системной энтропией (или мастер-сидом)
+    pub fn build(self) -> Result<Generator, String> {
+        let params = self.build_params()?;
+        let mut seed = match params.seed {
+            Some(master) => derive_seed(&master, 0),
+            None => {
+                let mut seed = [0u8; 32];
+                getrandom::fill(&mut seed)
+                    .map_err(|e| format!("нет доступа к системной энтропии: {}", e))?;
+                seed
+            }
+        };
+        let rng = match params.rounds {
+            12 => AnyChaCha::ChaCha12(ChaCha12Rng::from_seed(seed)),
+            20 => AnyChaCha::ChaCha20(ChaCha20Rng::from_seed(seed)),
+            _ => AnyChaCha::ChaCha8(ChaCha8Rng::from_seed(seed)),
+        };
+        seed.zeroize();
+        Ok(Generator { params, rng })
+    }
+}
+
+/// ChaCha с числом раундов, выбранным во время выполнения
+enum AnyChaCha {
+    ChaCha8(ChaCha8Rng),
+    ChaCha12(ChaCha12Rng),
+    ChaCha20(ChaCha20Rng),
+}
+
+impl RngCore for AnyChaCha {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            AnyChaCha::ChaCha8(r) => r.next_u32(),
+            AnyChaCha::ChaCha12(r) => r.next_u32(),
+            AnyChaCha::ChaCha20(r) => r.next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            AnyChaCha::ChaCha8(r) => r.next_u64(),
+            AnyChaCha::ChaCha12(r) => r.next_u64(),
+            AnyChaCha::ChaCha20(r) => r.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        match self {
+            AnyChaCha::ChaCha8(r) => r.fill_bytes(dst),
+            AnyChaCha::ChaCha12(r) => r.fill_bytes(dst),
+            AnyChaCha::ChaCha20(r) => r.fill_bytes(dst),
+        }
+    }
+}
+
+/// Генератор паролей для использования из кода.
+/// Как итератор бесконечен: каждый `next` — новый пароль.
+pub struct Generator {
+    params: GenParams,
+    rng: AnyChaCha,
+}
+
+impl Generator {
+    pub fn builder() -> GeneratorBuilder {
+        GeneratorBuilder::default()
+    }
+
+    /// # Panics
+    /// Если база утечек из `breach_db` не читается или отвергает все кандидаты.
+    /// Без `breach_db` не паникует; иначе используйте `try_generate`
+    pub fn generate(&mut self) -> Secret {
+        self.try_generate().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Новый пароль или ошибка проверки по базе утечек
+    pub fn try_generate(&mut self) -> Result<Secret, String> {
+        let mut buf = Vec::with_capacity(self.params.max_password_len());
+        unsafe {
+            let len = write_password(&mut self.rng, &self.params, buf.as_mut_ptr())?;
+            buf.set_len(len);
+        }
+        // Алфавиты — ASCII, словари — строки Rust, поэтому байты всегда валидный UTF-8.
+        // from_utf8 забирает буфер без копирования, так что копий пароля не остается
+        Ok(Secret::from(String::from_utf8(buf).expect("генератор пишет только UTF-8")))
+    }
+
+    pub fn params(&self) -> &GenParams {
+        &self.params
+    }
+
+    pub fn entropy_bits(&self) -> f64 {
+        self.params.entropy_bits()
+    }
+}
+
+impl Iterator for Generator {
+    type Item = Secret;
+
+    fn next(&mut self) -> Option<Secret> {
+        Some(self.generate())
+    }
+}
+
+/// Публичная точка входа. Выбирает алгоритм на основе rounds и вызывает generic-функцию.
+/// Ошибка возможна только с базой утечек: она не читается или отвергает все кандидаты
+pub fn generate_chunk(start_id: u64, size: u64, params: &GenParams) -> Result<SecretBuf, String> {
+    let mut buf = SecretBuf::with_capacity(size as usize * params.max_record_len());
+    chunk(start_id, size, params, false, &mut buf)?;
+    Ok(buf)
+}
+
+/// То же, что `generate_chunk`, но пишет в готовый буфер (например, из `SecretPool`).
+/// Буфер очищается; паника, если в нем меньше `size * max_record_len()` байт
+pub fn generate_chunk_into(start_id: u64, size: u64, params: &GenParams, buf: &mut SecretBuf) -> Result<(), String> {
+    buf.clear();
+    assert!(buf.capacity() >= size as usize * params.max_record_len(), "буфер меньше чанка");
+    chunk(start_id, size, params, false, buf)?;
+    Ok(())
+}
+
+/// То же, что `generate_chunk`, но дополнительно возвращает первый пароль чанка
+/// в исходном виде, до экранирования под формат вывода (для `--copy`)
+pub fn generate_chunk_keep_first(
+    start_id: u64,
+    size: u64,
+    params: &GenParams,
+) -> Result<(SecretBuf, Option<Secret>), String> {
+    let mut buf = SecretBuf::with_capacity(size as usize * params.max_record_len());
+    let first = chunk(start_id, size, params, true, &mut buf)?;
+    Ok((buf, first))
+}
+
+fn chunk(
+    start_id: u64,
+    size: u64,
+    params: &GenParams,
+    keep_first: bool,
+    buf: &mut SecretBuf,
+) -> Result<Option<Secret>, String> {
+    let Some(master) = params.seed else {
+        let mut seed = [0u8; 32];
+        // Используем системную энтропию для инициализации
+        let _ = getrandom::fill(&mut seed);
+        let first = block(&seed, 0, start_id, size, params, keep_first, buf);
+        seed.zeroize();
+        return first;
+    };
+
+    // С --seed поток каждого блока из SEED_BLOCK паролей зависит только от мастер-сида
+    // и номера блока, поэтому вывод не зависит ни от размера чанков (он меняется
+    // с --hash и длиной), ни от того, в каком потоке rayon выполнился чанк
+    let mut first = None;
+    let end = start_id + size;
+    let mut id = start_id;
+    while id < end {
+        let index = (id - 1) / SEED_BLOCK;
+        let block_start = index * SEED_BLOCK + 1;
+        let block_end = (block_start + SEED_BLOCK).min(end);
+        let mut seed = derive_seed(&master, index + 1);
+        let got = block(&seed, id - block_start, id, block_end - id, params, keep_first && id == start_id, buf);
+        seed.zeroize();
+        first = first.or(got?);
+        id = block_end;
     }
+    Ok(first)
+}
+
+/// Генерирует `size` паролей из одного сида, дописывая их в `buf`.
+/// Первые `skip` паролей потока генерируются вхолостую: так чанк, начатый
+/// посреди блока, продолжает тот же поток, что и чанк от начала блока
+fn block(
+    seed: &[u8; 32],
+    skip: u64,
+    start_id: u64,
+    size: u64,
+    params: &GenParams,
+    keep_first: bool,
+    buf: &mut SecretBuf,
+) -> Result<Option<Secret>, String> {
+    match params.rounds {
+        12 => run(ChaCha12Rng::from_seed(*seed), seed, skip, start_id, size, params, keep_first, buf),
+        20 => run(ChaCha20Rng::from_seed(*seed), seed, skip, start_id, size, params, keep_first, buf),
+        _ => run(ChaCha8Rng::from_seed(*seed), seed, skip, start_id, size, params, keep_first, buf),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run<R: RngCore>(
+    mut rng: R,
+    seed: &[u8; 32],
+    skip: u64,
+    start_id: u64,
+    size: u64,
+    params: &GenParams,
+    keep_first: bool,
+    buf: &mut SecretBuf,
+) -> Result<Option<Secret>, String> {
+    // Соли берутся из отдельного потока, чтобы --hash не менял сами пароли
+    let mut salt_rng = params
+        .hash
+        .map(|_| ChaCha20Rng::from_seed(derive_seed(seed, 1)));
+    if skip > 0 {
+        let mut skipped = SecretBuf::with_capacity(skip as usize * params.max_record_len());
+        generate_internal(&mut rng, &mut salt_rng, start_id - skip, skip, params, false, &mut skipped)?;
+    }
+    generate_internal(&mut rng, &mut salt_rng, start_id, size, params, keep_first, buf)
+}
+
+/// Выводит сид блока из мастер-сида: ChaCha20 с номером потока `stream`.
+/// Разные потоки одного ключа независимы, так что блоки не пересекаются.
+fn derive_seed(master: &[u8; 32], stream: u64) -> [u8; 32] {
+    let mut rng = ChaCha20Rng::from_seed(*master);
+    rng.set_stream(stream);
+    let mut seed = [0u8; 32];
+    rng.fill_bytes(&mut seed);
+    seed
 }
 
 /// Внутренняя функция с логикой генерации.
 /// <R: RngCore> означает, что она принимает любой генератор (8, 12 или 20 раундов),
 /// и компилятор создаст для каждого отдельную оптимизированную версию кода.
 fn generate_internal<R: RngCore>(
-    mut rng: R,
+    rng: &mut R,
+    salt_rng: &mut Option<ChaCha20Rng>,
     start_id: u64,
     size: u64,
-    length: usize,
-    fast_mode: bool,
-    word_mode: bool,
-    format: OutputFormat,
-) -> Vec<u8> {
-    // Резервируем память: длина пароля + макс. длина ID (20) + разделители
-    let mut buf = Vec::with_capacity(size as usize * (length + 32));
+    params: &GenParams,
+    keep_first: bool,
+    buf: &mut SecretBuf,
+) -> Result<Option<Secret>, String> {
+    let format = params.format;
+    // Быстрый путь для вывода по умолчанию, -f и -w: без префиксов, экранирования,
+    // хеша и проверок политики и утечек цикл по паролям остается плотным
+    let simple = params.pattern.is_none() && (params.word_mode || params.policy.is_empty());
+    if format == OutputFormat::Plain && params.hash.is_none() && params.breach.is_none() && simple && !keep_first {
+        unsafe {
+            let ptr: *mut u8 = buf.as_mut_ptr();
+            let mut offset = buf.len();
+            if params.word_mode {
+                for _ in 0..size {
+                    offset += write_words(rng, params, ptr.add(offset));
+                    *ptr.add(offset) = b'\n';
+                    offset += 1;
+                }
+            } else if params.fast_mode {
+                for _ in 0..size {
+                    offset += write_fast(rng, params.length, ptr.add(offset));
+                    *ptr.add(offset) = b'\n';
+                    offset += 1;
+                }
+            } else {
+                for _ in 0..size {
+                    for _ in 0..params.length {
+                        *ptr.add(offset) = pick(rng, &params.charset);
+                        offset += 1;
+                    }
+                    *ptr.add(offset) = b'\n';
+                    offset += 1;
+                }
+            }
+            debug_assert!(offset <= buf.capacity(), "запись вышла за бюджет max_record_len");
+            buf.set_len(offset);
+        }
+        return Ok(None);
+    }
+
+    let meta = if matches!(format, OutputFormat::JsonObjects | OutputFormat::Ndjson) {
+        params.json_meta()
+    } else {
+        Vec::new()
+    };
+
+    let layout = params.import_layout();
+    let mut escape = Escape::of(format, params.delimiter);
+    // Обычный вывод с хешем — это пара `пароль,хеш`, поэтому пароль в кавычках по CSV
+    if format == OutputFormat::Plain && params.hash.is_some() && !params.hash_only {
+        escape = Escape::Csv(b',');
+    }
+    // Если в алфавите нет ни одного спецсимвола, не сканируем каждый пароль.
+    // Хеш экранируется отдельно, поэтому с --hash оставляем как есть
+    let mut password_escape = escape;
+    let from_alphabet = params.pattern.is_none() && !params.word_mode;
+    if from_alphabet && !escape.needed_for(params.alphabet()) {
+        password_escape = Escape::None;
+    }
+    let field_sep = if format == OutputFormat::Csv { params.delimiter } else { b',' };
+
+    let mut first = None;
 
     unsafe {
         let ptr: *mut u8 = buf.as_mut_ptr();
-        let mut offset = 0;
+        let mut offset = buf.len();
 
         for i in 0..size {
             let current_id = start_id + i;
@@ -85,7 +739,7 @@ fn generate_internal<R: RngCore>(
             match format {
                 OutputFormat::Csv => {
                     offset += fast_write_u64_ptr(ptr.add(offset), current_id);
-                    *ptr.add(offset) = b',';
+                    *ptr.add(offset) = params.delimiter;
                     offset += 1;
                 }
                 OutputFormat::Json => {
@@ -93,106 +747,484 @@ fn generate_internal<R: RngCore>(
                     std::ptr::copy_nonoverlapping(prefix.as_ptr(), ptr.add(offset), prefix.len());
                     offset += prefix.len();
                 }
-                _ => {}
+                OutputFormat::JsonObjects | OutputFormat::Ndjson => {
+                    // В NDJSON каждая строка — самостоятельный объект без запятых,
+                    // поэтому чанки можно писать в любом порядке
+                    let prefix: &[u8] = match format {
+                        OutputFormat::Ndjson => b"{\"id\":",
+                        _ if current_id == 1 => b"\n  {\"id\":",
+                        _ => b",\n  {\"id\":",
+                    };
+                    offset += copy_bytes(ptr.add(offset), prefix);
+                    offset += fast_write_u64_ptr(ptr.add(offset), current_id);
+                    offset += copy_bytes(ptr.add(offset), b",\"password\":\"");
+                }
+                _ => {
+                    if let Some(layout) = &layout {
+                        if format == OutputFormat::Bitwarden && current_id != 1 {
+                            *ptr.add(offset) = b',';
+                            offset += 1;
+                        }
+                        for piece in &layout.before {
+                            offset += match piece {
+                                Piece::Bytes(b) => copy_bytes(ptr.add(offset), b),
+                                Piece::Id => fast_write_u64_ptr(ptr.add(offset), current_id),
+                            };
+                        }
+                    }
+                }
             }
 
             // 2. ГЕНЕРАЦИЯ КОНТЕНТА
-            if word_mode {
-                for k in 0..length {
-                    let random_u32 = rng.next_u32();
-                    // Умножение вместо деления по модулю для скорости и равномерности
-                    let idx = ((random_u32 as u64 * WORDLIST.len() as u64) >> 32) as usize;
-                    let word = *WORDLIST.get_unchecked(idx);
-
-                    std::ptr::copy_nonoverlapping(word.as_ptr(), ptr.add(offset), word.len());
-                    offset += word.len();
-
-                    if k < length - 1 {
-                        *ptr.add(offset) = b'-';
+            let written = write_password(rng, params, ptr.add(offset))?;
+            // Длина в символах: байты продолжения UTF-8 не считаем
+            let chars = if meta.is_empty() {
+                0
+            } else {
+                std::slice::from_raw_parts(ptr.add(offset), written)
+                    .iter()
+                    .filter(|&&b| b & 0xC0 != 0x80)
+                    .count()
+            };
+            if keep_first && i == 0 {
+                let raw = std::slice::from_raw_parts(ptr.add(offset), written);
+                first = Some(Secret::from(String::from_utf8_lossy(raw).into_owned()));
+            }
+            match (params.hash, salt_rng.as_mut()) {
+                (Some(algo), Some(salt_rng)) => {
+                    let raw = std::slice::from_raw_parts(ptr.add(offset), written);
+                    let hash = algo.hash(raw, salt_rng);
+                    if params.hash_only {
+                        // Пароль затирается хешем; хвост обнулит SecretBuf при освобождении
+                        if algo == HashAlgo::Htpasswd {
+                            let user = if params.username.is_empty() {
+                                &params.name_template
+                            } else {
+                                &params.username
+                            };
+                            let user = crate::import::render(user, current_id);
+                            offset += copy_bytes(ptr.add(offset), user.as_bytes());
+                            *ptr.add(offset) = b':';
+                            offset += 1;
+                        }
+                    } else {
+                        offset += escape_in_place(ptr.add(offset), written, password_escape);
+                        *ptr.add(offset) = field_sep;
                         offset += 1;
                     }
+                    // В PHC-строке argon2 есть запятые, поэтому хеш тоже экранируется
+                    let len = copy_bytes(ptr.add(offset), hash.as_bytes());
+                    offset += escape_in_place(ptr.add(offset), len, escape);
                 }
-            } else if fast_mode {
-                let mut current_len = length;
-
-                #[cfg(target_arch = "x86_64")]
-                if is_x86_feature_detected!("avx2") && current_len >= 32 {
-                    let chunks_32 = current_len / 32;
-                    let mut rand_buf = [0u8; 32];
-                    for _ in 0..chunks_32 {
-                        rng.fill_bytes(&mut rand_buf);
-                        // Убрал лишний unsafe внутри, так как мы уже в unsafe контексте
-                        crate::avx2::Avx2Mapper::map_64_symbols(rand_buf.as_ptr(), ptr.add(offset));
-                        offset += 32;
-                        current_len -= 32;
-                    }
-                }
+                _ => offset += escape_in_place(ptr.add(offset), written, password_escape),
+            }
 
-                if current_len >= 10 {
-                    let chunks_10 = current_len / 10;
-                    for _ in 0..chunks_10 {
-                        let r = rng.next_u64();
-                        *ptr.add(offset) = *CHARSET_FAST.get_unchecked((r & 63) as usize);
-                        *ptr.add(offset + 1) =
-                            *CHARSET_FAST.get_unchecked(((r >> 6) & 63) as usize);
-                        *ptr.add(offset + 2) =
-                            *CHARSET_FAST.get_unchecked(((r >> 12) & 63) as usize);
-                        *ptr.add(offset + 3) =
-                            *CHARSET_FAST.get_unchecked(((r >> 18) & 63) as usize);
-                        *ptr.add(offset + 4) =
-                            *CHARSET_FAST.get_unchecked(((r >> 24) & 63) as usize);
-                        *ptr.add(offset + 5) =
-                            *CHARSET_FAST.get_unchecked(((r >> 30) & 63) as usize);
-                        *ptr.add(offset + 6) =
-                            *CHARSET_FAST.get_unchecked(((r >> 36) & 63) as usize);
-                        *ptr.add(offset + 7) =
-                            *CHARSET_FAST.get_unchecked(((r >> 42) & 63) as usize);
-                        *ptr.add(offset + 8) =
-                            *CHARSET_FAST.get_unchecked(((r >> 48) & 63) as usize);
-                        *ptr.add(offset + 9) =
-                            *CHARSET_FAST.get_unchecked(((r >> 54) & 63) as usize);
-                        offset += 10;
-                        current_len -= 10;
+            // 3. ПОСТФИКСЫ
+            match format {
+                OutputFormat::Json => {
+                    *ptr.add(offset) = b'\"';
+                    offset += 1;
+                }
+                OutputFormat::JsonObjects | OutputFormat::Ndjson => {
+                    offset += copy_bytes(ptr.add(offset), b"\",\"length\":");
+                    offset += fast_write_u64_ptr(ptr.add(offset), chars as u64);
+                    offset += copy_bytes(ptr.add(offset), &meta);
+                    if format == OutputFormat::Ndjson {
+                        *ptr.add(offset) = b'\n';
+                        offset += 1;
                     }
                 }
-
-                if current_len > 0 {
-                    let mut r = rng.next_u64();
-                    for _ in 0..current_len {
-                        *ptr.add(offset) = *CHARSET_FAST.get_unchecked((r & 63) as usize);
-                        r >>= 6;
+                _ => match &layout {
+                    Some(layout) => offset += copy_bytes(ptr.add(offset), &layout.after),
+                    None => {
+                        *ptr.add(offset) = b'\n';
                         offset += 1;
                     }
+                },
+            }
+        }
+        debug_assert!(offset <= buf.capacity(), "запись вышла за бюджет max_record_len");
+        buf.set_len(offset);
+    }
+    Ok(first)
+}
+
+/// Как экранировать пароль внутри записи
+#[derive(Clone, Copy)]
+enum Escape {
+    None,
+    Csv(u8),
+    Json,
+    Xml,
+}
+
+impl Escape {
+    /// Есть ли в `alphabet` символы, которые придется экранировать
+    fn needed_for(self, alphabet: &[u8]) -> bool {
+        alphabet.iter().any(|&b| match self {
+            Escape::None => false,
+            Escape::Csv(delimiter) => b == delimiter || matches!(b, b'"' | b'\r' | b'\n'),
+            Escape::Json => b == b'"' || b == b'\\' || b < 0x20,
+            Escape::Xml => matches!(b, b'&' | b'<' | b'>'),
+        })
+    }
+
+    fn of(format: OutputFormat, delimiter: u8) -> Self {
+        match format {
+            OutputFormat::Plain => Escape::None,
+            OutputFormat::Csv => Escape::Csv(delimiter),
+            OutputFormat::KeepassCsv | OutputFormat::OnePasswordCsv => Escape::Csv(b','),
+            OutputFormat::Json
+            | OutputFormat::JsonObjects
+            | OutputFormat::Ndjson
+            | OutputFormat::Bitwarden => Escape::Json,
+            OutputFormat::KeepassXml => Escape::Xml,
+        }
+    }
+}
+
+/// Экранирует пароль длины `len`, уже записанный по адресу `ptr`, прямо в буфере,
+/// и возвращает новую длину. CSV — по RFC 4180 (кавычки и удвоение `"`),
+/// JSON — `\"`, `\\` и `\u00XX` для управляющих символов, XML — сущности `&amp;` `&lt;` `&gt;`.
+/// Текст только удлиняется, поэтому пишем с конца и не затираем непрочитанное;
+/// отдельной копии пароля при этом не появляется.
+unsafe fn escape_in_place(ptr: *mut u8, len: usize, escape: Escape) -> usize {
+    const HEX: &[u8] = b"0123456789abcdef";
+
+    let (quoted, extra) = {
+        let src = unsafe { std::slice::from_raw_parts(ptr, len) };
+        let extra: usize = match escape {
+            Escape::None => return len,
+            Escape::Csv(delimiter) => {
+                let special = |b: u8| b == delimiter || matches!(b, b'"' | b'\r' | b'\n');
+                if !src.iter().any(|&b| special(b)) {
+                    return len;
+                }
+                // Две обрамляющие кавычки плюс удвоение каждой внутренней
+                2 + src.iter().filter(|&&b| b == b'"').count()
+            }
+            Escape::Json => src
+                .iter()
+                .map(|&b| match b {
+                    b'"' | b'\\' => 1,
+                    0..0x20 => 5,
+                    _ => 0,
+                })
+                .sum(),
+            Escape::Xml => src
+                .iter()
+                .map(|&b| match b {
+                    b'&' => 4,
+                    b'<' | b'>' => 3,
+                    _ => 0,
+                })
+                .sum(),
+        };
+        if extra == 0 {
+            return len;
+        }
+        (matches!(escape, Escape::Csv(_)), extra)
+    };
+
+    let total = len + extra;
+    let mut j = total;
+    unsafe {
+        let mut put = |bytes: &[u8]| {
+            j -= bytes.len();
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr.add(j), bytes.len());
+        };
+        if quoted {
+            put(b"\"");
+        }
+        for i in (0..len).rev() {
+            let b = *ptr.add(i);
+            match (escape, b) {
+                (Escape::Csv(_), b'"') => put(b"\"\""),
+                (Escape::Json, b'"' | b'\\') => put(&[b'\\', b]),
+                (Escape::Json, 0..0x20) => put(&[
+                    b'\\',
+                    b'u',
+                    b'0',
+                    b'0',
+                    HEX[(b >> 4) as usize],
+                    HEX[(b & 0xF) as usize],
+                ]),
+                (Escape::Xml, b'&') => put(b"&amp;"),
+                (Escape::Xml, b'<') => put(b"&lt;"),
+                (Escape::Xml, b'>') => put(b"&gt;"),
+                _ => put(&[b]),
+            }
+        }
+        if quoted {
+            put(b"\"");
+        }
+    }
+    total
+}
+
+/// Копирует срез по адресу `dst` и возвращает число записанных байт
+#[inline(always)]
+unsafe fn copy_bytes(dst: *mut u8, src: &[u8]) -> usize {
+    unsafe { std::ptr::copy_nonoverlapping(src.as_ptr(), dst, src.len()) };
+    src.len()
+}
+
+/// После стольких паролей подряд из базы утечек генерация прерывается
+const MAX_BREACHED_RETRIES: u32 = 10_000;
+
+/// Предел попыток подобрать пароль под политику. `validate` отсекает политики
+/// с долей подходящих паролей меньше 1e-6, но не учитывает `max_repeat`
+const MAX_POLICY_RETRIES: u32 = 100_000_000;
+
+/// Пишет один пароль по адресу `ptr` и возвращает число записанных байт.
+/// Вызывающий обязан выделить не меньше `params.max_password_len()` байт.
+#[inline(always)]
+unsafe fn write_password<R: RngCore>(rng: &mut R, params: &GenParams, ptr: *mut u8) -> Result<usize, String> {
+    let GenParams {
+        length,
+        fast_mode,
+        word_mode,
+        ref charset,
+        ref policy,
+        ref pattern,
+        ..
+    } = *params;
+    let enforce_policy = !word_mode && pattern.is_none() && !policy.is_empty();
+    let mut breached = 0;
+    let mut rejected = 0;
+
+    unsafe {
+        let mut offset = 0;
+        loop {
+            if let Some(pattern) = pattern {
+                for slot in &pattern.slots {
+                    *ptr.add(offset) = match slot {
+                        Slot::Literal(b) => *b,
+                        Slot::Class(class) => pick(rng, class),
+                    };
+                    offset += 1;
                 }
+            } else if word_mode {
+                offset = write_words(rng, params, ptr);
+            } else if fast_mode {
+                offset = write_fast(rng, length, ptr);
             } else {
                 for _ in 0..length {
-                    let mut r = rng.next_u32();
-                    // Отсеивание (Rejection Sampling) для удаления Modulo Bias
-                    if r >= CHARSET_LIMIT {
-                        loop {
-                            r = rng.next_u32();
-                            if r < CHARSET_LIMIT {
-                                break;
-                            }
-                        }
-                    }
-                    *ptr.add(offset) = *CHARSET.get_unchecked((r % CHARSET_LEN as u32) as usize);
+                    *ptr.add(offset) = pick(rng, charset);
                     offset += 1;
                 }
             }
 
-            // 3. ПОСТФИКСЫ
-            if format == OutputFormat::Json {
-                *ptr.add(offset) = b'\"';
+            // Политика: отбраковываем пароль целиком, чтобы не вносить смещение
+            let content = std::slice::from_raw_parts(ptr, offset);
+            if !enforce_policy || policy.check(content) {
+                match &params.breach {
+                    None => break,
+                    Some(db) => match db.lookup(content) {
+                        Ok(None) => break,
+                        Ok(Some(_)) => breached += 1,
+                        Err(e) => return Err(format!("ошибка чтения базы утечек: {}", e)),
+                    },
+                }
+                // Для коротких PIN-кодов в утечках может оказаться все пространство
+                if breached >= MAX_BREACHED_RETRIES {
+                    return Err(format!(
+                        "{} кандидатов подряд найдены в базе утечек: увеличьте длину или алфавит",
+                        breached
+                    ));
+                }
+            } else {
+                rejected += 1;
+                if rejected >= MAX_POLICY_RETRIES {
+                    return Err(format!(
+                        "{} кандидатов подряд не прошли политику: увеличьте длину или ослабьте политику",
+                        rejected
+                    ));
+                }
+            }
+            offset = 0;
+        }
+        Ok(offset)
+    }
+}
+
+/// Фраза из `length` слов. Отдельная функция, чтобы быстрый путь в
+/// `generate_internal` звал ее напрямую, минуя проверки `write_password`
+#[inline(always)]
+unsafe fn write_words<R: RngCore>(rng: &mut R, params: &GenParams, ptr: *mut u8) -> usize {
+    let GenParams {
+        length,
+        ref phrase,
+        ref cased,
+        ..
+    } = *params;
+    let mut offset = 0;
+    unsafe {
+        let n = cased[0].len() as u32;
+        let slots = length as u32 + 1;
+        let digit_at = phrase.append_digit.then(|| uniform_index(rng, slots));
+        let symbol_at = phrase.append_symbol.then(|| uniform_index(rng, slots));
+
+        for k in 0..=length {
+            // Вставки приклеиваются к началу k-го слова или к концу фразы
+            if symbol_at == Some(k) {
+                let idx = uniform_index(rng, APPEND_SYMBOLS.len() as u32);
+                *ptr.add(offset) = *APPEND_SYMBOLS.get_unchecked(idx);
                 offset += 1;
+            }
+            if digit_at == Some(k) {
+                *ptr.add(offset) = b'0' + uniform_index(rng, 10) as u8;
+                offset += 1;
+            }
+            if k == length {
+                break;
+            }
+
+            let variant = if cased.len() > 1 {
+                cased.get_unchecked(uniform_index(rng, cased.len() as u32))
             } else {
-                *ptr.add(offset) = b'\n';
+                cased.get_unchecked(0)
+            };
+            let word = variant.words.get_unchecked(uniform_index(rng, n));
+
+            std::ptr::copy_nonoverlapping(word.as_ptr(), ptr.add(offset), word.len());
+            offset += word.len();
+
+            if k < length - 1 {
+                match &phrase.sep {
+                    Separator::Fixed(sep) => {
+                        std::ptr::copy_nonoverlapping(
+                            sep.as_ptr(),
+                            ptr.add(offset),
+                            sep.len(),
+                        );
+                        offset += sep.len();
+                    }
+                    Separator::Random => {
+                        let idx =
+                            uniform_index(rng, RANDOM_SEPARATORS.len() as u32);
+                        *ptr.add(offset) = *RANDOM_SEPARATORS.get_unchecked(idx);
+                        offset += 1;
+                    }
+                }
+            }
+        }
+    }
+    offset
+}
+
+/// Пароль `-f` из `CHARSET_FAST`: по 6 бит случайности на символ
+#[inline(always)]
+unsafe fn write_fast<R: RngCore>(rng: &mut R, length: usize, ptr: *mut u8) -> usize {
+    let mut offset = 0;
+    unsafe {
+        let mut current_len = length;
+
+        // Блоки по 32 символа: байт → CHARSET_FAST[байт & 63]. AVX2 лишь ускоряет
+        // то же отображение, поэтому RNG расходуется одинаково на любой машине
+        // и --seed / derive дают один и тот же пароль везде
+        if current_len >= 32 {
+            #[cfg(target_arch = "x86_64")]
+            let avx2 = is_x86_feature_detected!("avx2");
+            #[cfg(not(target_arch = "x86_64"))]
+            let avx2 = false;
+
+            let chunks_32 = current_len / 32;
+            let mut rand_buf = [0u8; 32];
+            for _ in 0..chunks_32 {
+                rng.fill_bytes(&mut rand_buf);
+                if avx2 {
+                    #[cfg(target_arch = "x86_64")]
+                    crate::avx2::Avx2Mapper::map_64_symbols(rand_buf.as_ptr(), ptr.add(offset));
+                } else {
+                    for (j, &b) in rand_buf.iter().enumerate() {
+                        *ptr.add(offset + j) = *CHARSET_FAST.get_unchecked((b & 63) as usize);
+                    }
+                }
+                offset += 32;
+                current_len -= 32;
+            }
+            // Случайные байты однозначно задают символы пароля
+            rand_buf.zeroize();
+        }
+
+        if current_len >= 10 {
+            let chunks_10 = current_len / 10;
+            for _ in 0..chunks_10 {
+                let r = rng.next_u64();
+                *ptr.add(offset) = *CHARSET_FAST.get_unchecked((r & 63) as usize);
+                *ptr.add(offset + 1) =
+                    *CHARSET_FAST.get_unchecked(((r >> 6) & 63) as usize);
+                *ptr.add(offset + 2) =
+                    *CHARSET_FAST.get_unchecked(((r >> 12) & 63) as usize);
+                *ptr.add(offset + 3) =
+                    *CHARSET_FAST.get_unchecked(((r >> 18) & 63) as usize);
+                *ptr.add(offset + 4) =
+                    *CHARSET_FAST.get_unchecked(((r >> 24) & 63) as usize);
+                *ptr.add(offset + 5) =
+                    *CHARSET_FAST.get_unchecked(((r >> 30) & 63) as usize);
+                *ptr.add(offset + 6) =
+                    *CHARSET_FAST.get_unchecked(((r >> 36) & 63) as usize);
+                *ptr.add(offset + 7) =
+                    *CHARSET_FAST.get_unchecked(((r >> 42) & 63) as usize);
+                *ptr.add(offset + 8) =
+                    *CHARSET_FAST.get_unchecked(((r >> 48) & 63) as usize);
+                *ptr.add(offset + 9) =
+                    *CHARSET_FAST.get_unchecked(((r >> 54) & 63) as usize);
+                offset += 10;
+                current_len -= 10;
+            }
+        }
+
+        if current_len > 0 {
+            let mut r = rng.next_u64();
+            for _ in 0..current_len {
+                *ptr.add(offset) = *CHARSET_FAST.get_unchecked((r & 63) as usize);
+                r >>= 6;
                 offset += 1;
             }
         }
-        buf.set_len(offset);
     }
-    buf
+    offset
+}
+
+/// Равномерный индекс в [0, n) методом Лемира.
+/// Умножение вместо деления по модулю, а редкие значения из «хвоста»,
+/// которые дали бы смещение при n, не делящем 2^32, отбрасываются.
+/// Подходит для любого размера алфавита или словаря, не только степени двойки.
+#[inline(always)]
+fn uniform_index<R: RngCore>(rng: &mut R, n: u32) -> usize {
+    let mut m = rng.next_u32() as u64 * n as u64;
+    let mut low = m as u32;
+    if low < n {
+        // 2^32 mod n — столько младших значений нужно отбросить
+        let threshold = n.wrapping_neg() % n;
+        while low < threshold {
+            m = rng.next_u32() as u64 * n as u64;
+            low = m as u32;
+        }
+    }
+    (m >> 32) as usize
+}
+
+/// Случайный символ алфавита без смещения
+#[inline(always)]
+fn pick<R: RngCore>(rng: &mut R, charset: &Charset) -> u8 {
+    let mut r = rng.next_u32();
+    // Отсеивание (Rejection Sampling) для удаления Modulo Bias
+    if r >= charset.limit {
+        loop {
+            r = rng.next_u32();
+            if r < charset.limit {
+                break;
+            }
+        }
+    }
+    unsafe {
+        *charset
+            .symbols
+            .get_unchecked((r % charset.symbols.len() as u32) as usize)
+    }
 }
 
 /// Супер-быстрая запись u64 через таблицу предзаписанных пар цифр
@@ -238,23 +1270,331 @@ unsafe fn fast_write_u64_ptr(ptr: *mut u8, mut n: u64) -> usize {
     len
 }
 
-pub fn copy_to_clipboard(pwd: &str) {
-    use std::io::Write;
-    use std::process::{Command, Stdio};
-    let _ = Command::new("wl-copy")
-        .stdin(Stdio::piped())
-        .arg("-n")
-        .spawn()
-        .and_then(|mut c| c.stdin.take().unwrap().write_all(pwd.as_bytes()));
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded() -> GeneratorBuilder {
+        Generator::builder().seed([7; 32])
+    }
+
+    /// Разбирает одно поле CSV в начале строки, возвращает значение и остаток
+    fn csv_unquote(line: &str, delimiter: char) -> (String, &str) {
+        let Some(quoted) = line.strip_prefix('"') else {
+            return match line.find(delimiter) {
+                Some(i) => (line[..i].to_string(), &line[i..]),
+                None => (line.to_string(), ""),
+            };
+        };
+        let mut out = String::new();
+        let mut chars = quoted.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            if c == '"' {
+                if chars.peek().map(|&(_, c)| c) == Some('"') {
+                    chars.next();
+                    out.push('"');
+                } else {
+                    return (out, &quoted[i + 1..]);
+                }
+            } else {
+                out.push(c);
+            }
+        }
+        panic!("незакрытая кавычка в '{}'", line);
+    }
+
+    /// Разбирает строку JSON, начинающуюся сразу после открывающей `"`
+    fn json_unquote(text: &str) -> (String, &str) {
+        let mut out = String::new();
+        let mut chars = text.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return (out, &text[i + 1..]),
+                '\\' => match chars.next() {
+                    Some((_, 'u')) => {
+                        let hex: String = (0..4).filter_map(|_| chars.next()).map(|(_, c)| c).collect();
+                        out.push(char::from_u32(u32::from_str_radix(&hex, 16).unwrap()).unwrap());
+                    }
+                    Some((_, c @ ('"' | '\\' | '/'))) => out.push(c),
+                    other => panic!("неизвестная escape-последовательность {:?}", other),
+                },
+                c if (c as u32) < 0x20 => panic!("неэкранированный управляющий символ в '{}'", text),
+                c => out.push(c),
+            }
+        }
+        panic!("незакрытая строка JSON '{}'", text);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn avx2_mapping_matches_scalar() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+        // Все 256 значений байта: AVX2 обязан давать CHARSET_FAST[b & 63]
+        for block in 0..8u8 {
+            let random: Vec<u8> = (0..32).map(|i| block * 32 + i).collect();
+            let mut out = [0u8; 32];
+            unsafe { crate::avx2::Avx2Mapper::map_64_symbols(random.as_ptr(), out.as_mut_ptr()) };
+            let expected: Vec<u8> = random.iter().map(|&b| CHARSET_FAST[(b & 63) as usize]).collect();
+            assert_eq!(out.as_slice(), expected.as_slice());
+        }
+    }
+
+    #[test]
+    fn plain_hash_budget_covers_doubled_quotes() {
+        // Регрессия: пароль из одних `"` удваивается при экранировании,
+        // и запись выходила за пределы буфера чанка
+        let params = seeded()
+            .length(200)
+            .charset(Charset::parse("\"a").unwrap())
+            .hash(HashAlgo::Sha512Crypt)
+            .build_params()
+            .unwrap();
+        let buf = generate_chunk(1, 8, &params).unwrap();
+        assert!(buf.len() <= 8 * params.max_record_len());
+
+        let text = std::str::from_utf8(&buf).unwrap();
+        assert_eq!(text.lines().count(), 8);
+        for line in text.lines() {
+            assert!(line.len() <= params.max_record_len());
+            let (password, rest) = csv_unquote(line, ',');
+            assert_eq!(password.len(), 200);
+            assert!(password.bytes().all(|b| b == b'"' || b == b'a'));
+            assert!(rest.starts_with(",$6$"));
+        }
+    }
+
+    #[test]
+    fn seeded_output_ignores_chunking() {
+        // С --seed пароли зависят только от номера, а не от того, как main нарезал
+        // партию: с --hash чанки мельче, и раньше пароли расходились с 9-го
+        let plain = seeded().length(10).build_params().unwrap();
+        let whole = generate_chunk(1, 40, &plain).unwrap();
+        let mut pieces = Vec::new();
+        for (start, size) in [(1, 3), (4, 8), (12, 21), (33, 8)] {
+            pieces.extend_from_slice(&generate_chunk(start, size, &plain).unwrap());
+        }
+        assert_eq!(&whole[..], pieces.as_slice());
+
+        let hashed = seeded().length(10).hash(HashAlgo::Sha512Crypt).build_params().unwrap();
+        let mut passwords = Vec::new();
+        for start in (1..=40).step_by(SEED_BLOCK as usize) {
+            let buf = generate_chunk(start, SEED_BLOCK, &hashed).unwrap();
+            for line in std::str::from_utf8(&buf).unwrap().lines() {
+                passwords.push(csv_unquote(line, ',').0);
+            }
+        }
+        let expected: Vec<&str> = std::str::from_utf8(&whole).unwrap().lines().collect();
+        assert_eq!(passwords, expected);
+    }
 
-pub fn print_report(start: Instant, count: u64, _length: usize, l: &I18n) {
-    let dur = start.elapsed().as_secs_f64();
-    if dur > 0.0 {
-        let speed = count as f64 / dur;
-        eprintln!("\n--- {} ---", l.stat_title);
-        eprintln!("{}: {:.4} s", l.stat_time, dur);
-        eprintln!("{}: {:.2} p/s", l.stat_speed, speed);
-        eprintln!("{}: {:.2} Mp/s", l.stat_perf, speed / 1_000_000.0);
+    #[test]
+    fn fast_path_matches_general_path() {
+        // С keep_first чанк идет общим путем, без него — быстрым; вывод один и тот же
+        for mode in [Mode::Chars, Mode::Fast, Mode::Words] {
+            let params = seeded().mode(mode).length(5).build_params().unwrap();
+            let fast = generate_chunk(3, 20, &params).unwrap();
+            let (general, _) = generate_chunk_keep_first(3, 20, &params).unwrap();
+            assert_eq!(&fast[..], &general[..]);
+        }
+        // Длина больше 32 проходит через блоки AVX2
+        let params = seeded().mode(Mode::Fast).length(70).build_params().unwrap();
+        assert_eq!(&generate_chunk(1, 8, &params).unwrap()[..], &generate_chunk_keep_first(1, 8, &params).unwrap().0[..]);
+    }
+
+    /// Отдает заранее заданные значения next_u32 по порядку;
+    /// next_u64 и fill_bytes собираются из тех же значений
+    struct Script(std::vec::IntoIter<u32>);
+
+    impl RngCore for Script {
+        fn next_u32(&mut self) -> u32 {
+            self.0.next().expect("сценарий исчерпан")
+        }
+        fn next_u64(&mut self) -> u64 {
+            rand_core::impls::next_u64_via_u32(self)
+        }
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            rand_core::impls::fill_bytes_via_next(self, dest)
+        }
+    }
+
+    #[test]
+    fn uniform_index_rejects_below_threshold() {
+        // n = 3·2^30: 2^32 mod n = 2^30, младшая половина произведения равна
+        // (x·3 mod 4)·2^30, так что отбрасываются ровно x, кратные 4
+        let n = 3 << 30;
+        let mut rng = Script(vec![0, 4, 8, 2].into_iter());
+        assert_eq!(uniform_index(&mut rng, n), 1);
+        assert_eq!(rng.0.len(), 0);
+
+        // x = 3 дает младшую половину ровно 2^30 — на пороге уже принимается
+        let mut rng = Script(vec![3].into_iter());
+        assert_eq!(uniform_index(&mut rng, n), 2);
+
+        // Для степени двойки порог 0 и ничего не отбрасывается
+        let mut rng = Script(vec![0].into_iter());
+        assert_eq!(uniform_index(&mut rng, 64), 0);
+    }
+
+    #[test]
+    fn uniform_index_chi_square() {
+        // n не степень двойки: при простом `% n` младшие индексы выпадали бы чаще
+        const N: usize = 1959;
+        const PER_BUCKET: usize = 200;
+        let mut rng = ChaCha8Rng::from_seed([42; 32]);
+        let mut counts = [0u32; N];
+        for _ in 0..N * PER_BUCKET {
+            counts[uniform_index(&mut rng, N as u32)] += 1;
+        }
+        let expected = PER_BUCKET as f64;
+        let chi2: f64 = counts.iter().map(|&c| (c as f64 - expected).powi(2) / expected).sum();
+        // N-1 степеней свободы: среднее 1958, σ = √(2·1958) ≈ 63. Допуск 5σ
+        let df = (N - 1) as f64;
+        let sigma = (2.0 * df).sqrt();
+        assert!((chi2 - df).abs() < 5.0 * sigma, "chi2 = {}", chi2);
+    }
+
+    #[test]
+    fn escaped_output_round_trips() {
+        // Все символы CHARSET плюс то, что ломает форматы: кавычка и обратный слеш.
+        // Табуляция и прочие управляющие байты в алфавит не допускаются, но бывают разделителем TSV
+        let mut symbols = CHARSET.to_vec();
+        symbols.extend_from_slice(b"\"\\");
+        let builder = || seeded().length(40).charset(Charset::new(symbols.clone()).unwrap());
+        let records = |params: GenParams| {
+            let buf = generate_chunk(1, 64, &params).unwrap();
+            String::from_utf8(buf.to_vec()).unwrap()
+        };
+
+        let plain = records(builder().build_params().unwrap());
+        let expected: Vec<&str> = plain.lines().collect();
+        assert_eq!(expected.len(), 64);
+        for &b in &symbols {
+            assert!(plain.as_bytes().contains(&b), "символ {:?} не встретился", b as char);
+        }
+
+        for delimiter in [',', ';', '\t', '|'] {
+            let params = builder().format(OutputFormat::Csv).delimiter(delimiter as u8).build_params().unwrap();
+            let text = records(params);
+            let mut passwords = Vec::new();
+            for (i, line) in text.lines().enumerate() {
+                let rest = line.strip_prefix(&format!("{}{}", i + 1, delimiter)).unwrap();
+                let (password, rest) = csv_unquote(rest, delimiter);
+                assert_eq!(rest, "", "лишнее поле в CSV с разделителем {:?}", delimiter);
+                passwords.push(password);
+            }
+            assert_eq!(passwords, expected, "CSV с разделителем {:?}", delimiter);
+        }
+
+        let text = records(builder().format(OutputFormat::Json).build_params().unwrap());
+        let passwords: Vec<String> = text
+            .lines()
+            .map(|line| {
+                let quoted = line.strip_prefix("  \"").unwrap();
+                let (password, rest) = json_unquote(quoted);
+                assert!(rest.is_empty() || rest == ",", "хвост '{}'", rest);
+                password
+            })
+            .collect();
+        assert_eq!(passwords, expected);
+
+        let text = records(builder().format(OutputFormat::Ndjson).build_params().unwrap());
+        let passwords: Vec<String> = text
+            .lines()
+            .map(|line| {
+                let (_, quoted) = line.split_once("\"password\":\"").unwrap();
+                let (password, rest) = json_unquote(quoted);
+                assert!(rest.starts_with(",\"length\":40"), "хвост '{}'", rest);
+                password
+            })
+            .collect();
+        assert_eq!(passwords, expected);
+    }
+
+    #[test]
+    fn breached_space_is_an_error() {
+        // Все пароли из алфавита `ab` длины 1 есть в базе: вместо паники — ошибка
+        use sha1::{Digest, Sha1};
+        let mut lines: Vec<String> = ["a", "b"]
+            .iter()
+            .map(|p| {
+                let hex: String = Sha1::digest(p.as_bytes()).iter().map(|b| format!("{:02X}", b)).collect();
+                format!("{}:1", hex)
+            })
+            .collect();
+        lines.sort();
+        let path = std::env::temp_dir().join(format!("passwg-breach-{}.txt", std::process::id()));
+        std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+        let builder = || {
+            seeded()
+                .length(1)
+                .charset(Charset::parse("ab").unwrap())
+                .breach_db(path.to_str().unwrap())
+        };
+        let error = generate_chunk(1, 8, &builder().build_params().unwrap()).err().unwrap();
+        assert!(error.contains("базе утечек"), "{}", error);
+        assert!(builder().build().unwrap().try_generate().is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn fit_length_rejects_missing_class() {
+        // Класса upper нет среди цифр: подбор длины не должен зацикливаться
+        let error = seeded()
+            .charset(Charset::parse("digits").unwrap())
+            .policy(Policy::parse("upper=1").unwrap())
+            .target_bits(64.0)
+            .build_params()
+            .err()
+            .unwrap();
+        assert!(error.contains("upper"), "{}", error);
+
+        let params = seeded()
+            .charset(Charset::parse("ab").unwrap())
+            .target_bits(64.0)
+            .build_params()
+            .unwrap();
+        assert_eq!(params.length, 64);
+        assert!(seeded().charset(Charset::parse("ab").unwrap()).target_bits(1e6).build_params().is_err());
+    }
+
+    #[test]
+    fn control_bytes_fit_every_format() {
+        // Charset::new такие байты не пропускает, но поля алфавита публичны.
+        // Каждый байт экранируется в \u00XX — худший случай для бюджета записи
+        let symbols: Vec<u8> = (1..0x20).collect();
+        let charset = Charset { limit: (u32::MAX / symbols.len() as u32) * symbols.len() as u32, symbols };
+        let formats = [
+            OutputFormat::Plain,
+            OutputFormat::Json,
+            OutputFormat::JsonObjects,
+            OutputFormat::Ndjson,
+            OutputFormat::Csv,
+            OutputFormat::KeepassCsv,
+            OutputFormat::KeepassXml,
+            OutputFormat::Bitwarden,
+            OutputFormat::OnePasswordCsv,
+        ];
+        for format in formats {
+            // --hash бывает только с обычным выводом и CSV
+            let hashes: &[_] = match format {
+                OutputFormat::Plain | OutputFormat::Csv => &[None, Some(HashAlgo::Sha512Crypt)],
+                _ => &[None],
+            };
+            for &hash in hashes {
+                let mut builder = seeded().length(4).charset(charset.clone()).format(format);
+                if let Some(algo) = hash {
+                    builder = builder.hash(algo);
+                }
+                let params = builder.build_params().unwrap();
+                let buf = generate_chunk(2, 64, &params).unwrap();
+                assert!(buf.len() <= 64 * params.max_record_len());
+            }
+        }
+        assert!(Charset::new(vec![1, 2]).is_err());
+        assert!(Charset::new(b"a\tb".to_vec()).is_err());
+        assert!(Charset::new(b"aa".to_vec()).is_err());
     }
 }
@@ -0,0 +1,256 @@
+use argon2::password_hash::{PasswordHasher, SaltString};
+use rand_core::RngCore;
+use sha2::{Digest, Sha512};
+use zeroize::Zeroize;
+
+/// Алфавит crypt(3): и соль sha512crypt, и сам хеш кодируются им
+const CRYPT_B64: &[u8] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+/// Стоимость bcrypt по умолчанию (2^12 итераций), как в самом крейте bcrypt
+pub const BCRYPT_COST: u32 = 12;
+/// Число раундов sha512crypt по умолчанию; в строку хеша оно не пишется
+const SHA512_ROUNDS: usize = 5000;
+
+/// Алгоритм хеширования для `--hash`
+#[derive(Clone, Copy, PartialEq)]
+pub enum HashAlgo {
+    /// `$6$` — формат /etc/shadow
+    Sha512Crypt,
+    /// `$2b$`
+    Bcrypt,
+    /// PHC-строка `$argon2id$v=19$...`
+    Argon2id,
+    /// Строка `пользователь:$2y$...` для Apache htpasswd
+    Htpasswd,
+}
+
+impl HashAlgo {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "sha512crypt" => Ok(HashAlgo::Sha512Crypt),
+            "bcrypt" => Ok(HashAlgo::Bcrypt),
+            "argon2id" => Ok(HashAlgo::Argon2id),
+            "htpasswd" => Ok(HashAlgo::Htpasswd),
+            other => Err(format!(
+                "неизвестный алгоритм хеширования '{}'. Допустимо: sha512crypt, bcrypt, argon2id, htpasswd",
+                other
+            )),
+        }
+    }
+
+    /// bcrypt молча отбрасывает все после 72-го байта пароля
+    pub fn truncates_at(self) -> Option<usize> {
+        match self {
+            HashAlgo::Bcrypt | HashAlgo::Htpasswd => Some(72),
+            _ => None,
+        }
+    }
+
+    /// Верхняя граница длины хеша в байтах (без имени пользователя htpasswd)
+    pub fn max_len(self) -> usize {
+        match self {
+            HashAlgo::Sha512Crypt => 3 + 16 + 1 + 86,
+            HashAlgo::Bcrypt | HashAlgo::Htpasswd => 60,
+            HashAlgo::Argon2id => 128,
+        }
+    }
+
+    /// Хеширует пароль со случайной солью из `rng`
+    pub fn hash<R: RngCore>(self, password: &[u8], rng: &mut R) -> String {
+        let mut salt = [0u8; 16];
+        rng.fill_bytes(&mut salt);
+        match self {
+            HashAlgo::Sha512Crypt => {
+                // 64 делит 256, поэтому маска дает равномерный выбор
+                let salt: Vec<u8> = salt.iter().map(|b| CRYPT_B64[(b & 0x3F) as usize]).collect();
+                sha512_crypt(password, &salt)
+            }
+            HashAlgo::Bcrypt | HashAlgo::Htpasswd => {
+                let version = if self == HashAlgo::Htpasswd {
+                    bcrypt::Version::TwoY
+                } else {
+                    bcrypt::Version::TwoB
+                };
+                bcrypt::hash_with_salt(password, BCRYPT_COST, salt)
+                    .expect("стоимость bcrypt задана константой и корректна")
+                    .format_for_version(version)
+            }
+            HashAlgo::Argon2id => {
+                let salt = SaltString::encode_b64(&salt).expect("16 байт соли всегда кодируются");
+                argon2::Argon2::default()
+                    .hash_password(password, &salt)
+                    .expect("параметры Argon2 по умолчанию корректны")
+                    .to_string()
+            }
+        }
+    }
+}
+
+/// SHA-crypt на SHA-512 по спецификации Ульриха Дреппера
+/// (https://www.akkadia.org/drepper/SHA-crypt.txt) с 5000 раундов.
+/// Соль — до 16 символов из алфавита crypt(3).
+pub fn sha512_crypt(password: &[u8], salt: &[u8]) -> String {
+    sha512_crypt_rounds(password, salt, SHA512_ROUNDS)
+}
+
+/// То же с заданным числом раундов; отличное от 5000 пишется в строку как `rounds=N$`
+fn sha512_crypt_rounds(password: &[u8], salt: &[u8], rounds: usize) -> String {
+    let salt = &salt[..salt.len().min(16)];
+    let plen = password.len();
+
+    let b = Sha512::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(password)
+        .finalize();
+
+    let mut ctx = Sha512::new().chain_update(password).chain_update(salt);
+    let mut cnt = plen;
+    while cnt > 64 {
+        ctx.update(b);
+        cnt -= 64;
+    }
+    ctx.update(&b[..cnt]);
+    // Биты длины пароля от младшего: 1 — добавляем B, 0 — сам пароль
+    let mut cnt = plen;
+    while cnt > 0 {
+        if cnt & 1 == 1 {
+            ctx.update(b);
+        } else {
+            ctx.update(password);
+        }
+        cnt >>= 1;
+    }
+    let mut a = ctx.finalize();
+
+    let mut ctx = Sha512::new();
+    for _ in 0..plen {
+        ctx.update(password);
+    }
+    let dp = ctx.finalize();
+    let mut p: Vec<u8> = dp.iter().cycle().take(plen).copied().collect();
+
+    let mut ctx = Sha512::new();
+    for _ in 0..16 + a[0] as usize {
+        ctx.update(salt);
+    }
+    let ds = ctx.finalize();
+    let s = &ds[..salt.len()];
+
+    for round in 0..rounds {
+        let mut ctx = Sha512::new();
+        if round & 1 == 1 {
+            ctx.update(&p);
+        } else {
+            ctx.update(a);
+        }
+        if round % 3 != 0 {
+            ctx.update(s);
+        }
+        if round % 7 != 0 {
+            ctx.update(&p);
+        }
+        if round & 1 == 1 {
+            ctx.update(a);
+        } else {
+            ctx.update(&p);
+        }
+        a = ctx.finalize();
+    }
+
+    // Байты переставляются тройками в порядке, заданном спецификацией
+    const ORDER: [(usize, usize, usize); 21] = [
+        (0, 21, 42),
+        (22, 43, 1),
+        (44, 2, 23),
+        (3, 24, 45),
+        (25, 46, 4),
+        (47, 5, 26),
+        (6, 27, 48),
+        (28, 49, 7),
+        (50, 8, 29),
+        (9, 30, 51),
+        (31, 52, 10),
+        (53, 11, 32),
+        (12, 33, 54),
+        (34, 55, 13),
+        (56, 14, 35),
+        (15, 36, 57),
+        (37, 58, 16),
+        (59, 17, 38),
+        (18, 39, 60),
+        (40, 61, 19),
+        (62, 20, 41),
+    ];
+    let mut out = String::with_capacity(3 + 20 + salt.len() + 1 + 86);
+    out.push_str("$6$");
+    if rounds != SHA512_ROUNDS {
+        out.push_str(&format!("rounds={}$", rounds));
+    }
+    out.push_str(std::str::from_utf8(salt).unwrap_or_default());
+    out.push('$');
+    for (i, j, k) in ORDER {
+        push_b64(&mut out, a[i], a[j], a[k], 4);
+    }
+    push_b64(&mut out, 0, 0, a[63], 2);
+    // Последовательность P выведена из пароля напрямую
+    p.zeroize();
+    out
+}
+
+fn push_b64(out: &mut String, b2: u8, b1: u8, b0: u8, n: usize) {
+    let mut w = ((b2 as u32) << 16) | ((b1 as u32) << 8) | b0 as u32;
+    for _ in 0..n {
+        out.push(CRYPT_B64[(w & 0x3F) as usize] as char);
+        w >>= 6;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::SeedableRng;
+
+    #[test]
+    fn sha512_crypt_known_answers() {
+        // Векторы из спецификации SHA-crypt
+        assert_eq!(
+            sha512_crypt(b"Hello world!", b"saltstring"),
+            "$6$saltstring$svn8UoSVapNtMuq1ukKS4tPQd8iKwSMHWjl/O817G3uBnIFNjnQJuesI68u4OTLiBFdcbYEdFCoEOfaS35inz1"
+        );
+        // Соль длиннее 16 символов обрезается
+        assert_eq!(
+            sha512_crypt_rounds(b"Hello world!", b"saltstringsaltstring", 10000),
+            "$6$rounds=10000$saltstringsaltst$OW1/O6BYHV6BcXZu8QVeXbDWra3Oeqh0sbHbbMCVNSnCM/UrjmM0Dp8vOuZeHBy/YTBmSK6H9qs/y3RnOaw5v."
+        );
+    }
+
+    #[test]
+    fn hashes_verify() {
+        use argon2::password_hash::{PasswordHash, PasswordVerifier};
+        let mut rng = ChaCha20Rng::from_seed([1; 32]);
+        let password = b"correct horse";
+
+        let hash = HashAlgo::Sha512Crypt.hash(password, &mut rng);
+        let salt = &hash[3..hash.rfind('$').unwrap()];
+        assert_eq!(salt.len(), 16);
+        assert_eq!(hash, sha512_crypt(password, salt.as_bytes()));
+        assert!(hash.len() <= HashAlgo::Sha512Crypt.max_len());
+
+        for algo in [HashAlgo::Bcrypt, HashAlgo::Htpasswd] {
+            let hash = algo.hash(password, &mut rng);
+            let prefix = if algo == HashAlgo::Bcrypt { "$2b$12$" } else { "$2y$12$" };
+            assert!(hash.starts_with(prefix), "{}", hash);
+            assert!(bcrypt::verify(password, &hash).unwrap());
+            assert!(!bcrypt::verify(b"wrong", &hash).unwrap());
+            assert!(hash.len() <= algo.max_len());
+        }
+
+        let hash = HashAlgo::Argon2id.hash(password, &mut rng);
+        assert!(hash.starts_with("$argon2id$v=19$"), "{}", hash);
+        let parsed = PasswordHash::new(&hash).unwrap();
+        assert!(argon2::Argon2::default().verify_password(password, &parsed).is_ok());
+        assert!(argon2::Argon2::default().verify_password(b"wrong", &parsed).is_err());
+        assert!(hash.len() <= HashAlgo::Argon2id.max_len());
+    }
+}
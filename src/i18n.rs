@@ -7,16 +7,46 @@ pub struct I18n {
     pub help_flags: &'static str,
     pub help_out: &'static str,
     pub help_json: &'static str,
+    pub help_json_objects: &'static str,
+    pub help_ndjson: &'static str,
     pub help_csv: &'static str,
+    pub help_tsv: &'static str,
+    pub help_delimiter: &'static str,
+    pub help_import: &'static str,
+    pub help_name_template: &'static str,
+    pub help_hash: &'static str,
+    pub help_hibp: &'static str,
     pub help_stats: &'static str,
     pub help_fast: &'static str,
     pub help_copy: &'static str,
     pub help_rounds: &'static str,
+    pub help_length_count: &'static str,
+    pub help_profile: &'static str,
+    pub help_charset: &'static str,
+    pub help_no_ambiguous: &'static str,
+    pub help_require: &'static str,
+    pub help_policy: &'static str,
+    pub help_pattern: &'static str,
+    pub help_bits: &'static str,
+    pub help_wordlist: &'static str,
+    pub help_sep: &'static str,
+    pub help_case: &'static str,
+    pub help_append: &'static str,
+    pub help_seed: &'static str,
+    pub help_ordered: &'static str,
+    pub help_clear_after: &'static str,
     pub help_h: &'static str,
+    pub help_syntax: &'static str,
+    pub help_derive: &'static str,
+    pub help_check: &'static str,
+    pub help_validate: &'static str,
+    pub derive_prompt: &'static str,
     pub stat_title: &'static str,
     pub stat_time: &'static str,
     pub stat_speed: &'static str,
     pub stat_perf: &'static str,
+    pub stat_entropy: &'static str,
+    pub stat_length: &'static str,
 }
 
 pub const EN: I18n = I18n {
@@ -25,18 +55,48 @@ pub const EN: I18n = I18n {
     help_len: "  length         Password length (default 16)",
     help_count: "  count          Number of passwords (default 1)",
     help_flags: "Flags:",
-    help_out: "  -o <file>      Write output to file",
+    help_out: "  -o, --output <file> Write output to file",
     help_json: "  --json         Output as JSON array",
-    help_csv: "  --csv          Output as CSV",
+    help_json_objects: "  --json-objects JSON with metadata: id, length, entropy, charset, mode",
+    help_ndjson: "  --ndjson       One JSON object per line (JSON Lines), for streaming",
+    help_csv: "  --csv          Output as CSV (RFC 4180 quoting)",
+    help_tsv: "  --tsv          Output as tab-separated values",
+    help_delimiter: "  --delimiter <c> Field delimiter for CSV, e.g. ';'",
+    help_import: "  --keepass-csv, --keepass-xml, --bitwarden, --1password  Password manager import formats",
+    help_name_template: "  --name-template <t>, --username <u>  Entry name (default passwg-{id}) and login; {id} is the number",
+    help_hash: "  --hash <algo>  Add a hash: sha512crypt, bcrypt, argon2id, htpasswd; --hash-only drops the password",
+    help_hibp: "  --hibp <file>  Regenerate passwords found in a local Pwned Passwords file (SHA-1 or NTLM)",
     help_stats: "  -s, --stats    Show performance statistics",
     help_fast: "  -f, --fast     Max speed mode (A-Z, a-z, 0-9, _, -)",
-    help_copy: "  -c, --copy     Copy one password to clipboard (Wayland only)",
+    help_copy: "  -c, --copy     Copy one password to the clipboard (X11, Wayland, macOS, Windows)",
     help_rounds: "  -r, --rounds   ChaCha rounds (8, 12, 20). Default: 8",
+    help_length_count: "  --length <n>, --count <n>  Length and count as flags; positional numbers win. A length from the config file does not apply to -w",
+    help_profile: "  --profile <name> Use [profile.<name>] from ~/.config/passwg/config.toml; top-level keys are defaults, flags override the file. --no-fast, --no-stats, --no-copy, --no-words, --ambiguous, --plain undo switches set there",
+    help_charset: "  --charset <s>  Custom alphabet: ranges (a-zA-Z0-9@#) and classes (lower,upper,digits,symbols)",
+    help_no_ambiguous: "  --no-ambiguous Exclude look-alike characters (0 O o 1 l I | ` ' \")",
+    help_require: "  --require <p>  Minimum per class, e.g. upper=2,digit=1,symbol=1",
+    help_policy: "  --policy <file> Policy file (TOML): length bounds, [require] minimums, forbidden chars, max_repeat",
+    help_pattern: "  --pattern <m>  Mask: ?l ?u ?d ?s ?a ?h ?H, rest is literal (count comes first)",
+    help_bits: "  --bits <n>     Pick length (or words) for n bits of entropy (count comes first)",
+    help_wordlist: "  --wordlist <f> Word list for -w: bip39, ru, ru-translit, eff-large, eff-short or a file (one word per line / diceware)",
+    help_sep: "  --sep <s>      Word separator for -w (default '-'), 'random' picks from ' ._-'",
+    help_case: "  --case <c>     Word case for -w: lower, title, upper, random",
+    help_append: "  --append-digit, --append-symbol  Insert a random digit / symbol into the phrase",
+    help_seed: "  --seed <hex>   Reproducible output for tests. INSECURE: never use for real secrets",
+    help_ordered: "  --ordered, --unordered  Keep ids in order (default for CSV/JSON) or write as ready",
+    help_clear_after: "  --clear-after <secs>    With -c: clear the clipboard after N seconds if it still holds the password",
     help_h: "  -h, --help     Show this help",
+    help_syntax: "Flags take values as --flag value or --flag=value; short flags combine (-sf, -r20, -ofile); -- ends flags.\nEnvironment: PASSWG_ROUNDS, PASSWG_LENGTH, PASSWG_COUNT, PASSWG_CHARSET and other PASSWG_<FLAG> override the config file; flags override the environment",
+    help_derive: "passwg derive <site> [--login L] [--counter N] [length] [flags]\n  Derive a stable site password from a master password (Argon2id), no vault needed",
+    help_check: "passwg check [--hibp <file>] [--json|--ndjson|--csv] [passwords.txt]\n  Estimate strength of passwords from stdin or a file (zxcvbn-style) and look them up in a local Pwned Passwords file",
+    help_validate: "passwg validate --policy <file> [passwords.txt]\n  Check passwords from stdin or a file against the same policy file used for generation; prints line numbers and reasons",
+    derive_prompt: "Master password: ",
     stat_title: "STATISTICS",
     stat_time: "Execution time:   ",
     stat_speed: "Stream speed:     ",
     stat_perf: "Performance:      ",
+    stat_entropy: "Entropy:          ",
+    stat_length: "length",
 };
 
 pub const RU: I18n = I18n {
@@ -45,18 +105,48 @@ pub const RU: I18n = I18n {
     help_len: "  длина          Длина пароля (по умолчанию 16)",
     help_count: "  количество     Количество паролей (по умолчанию 1)",
     help_flags: "Флаги:",
-    help_out: "  -o <file>      Записать вывод в файл",
+    help_out: "  -o, --output <файл> Записать вывод в файл",
     help_json: "  --json         Вывод в формате JSON массив",
-    help_csv: "  --csv          Вывод в формате CSV",
+    help_json_objects: "  --json-objects JSON с метаданными: id, длина, энтропия, алфавит, режим",
+    help_ndjson: "  --ndjson       Один JSON-объект на строку (JSON Lines), для потоковой обработки",
+    help_csv: "  --csv          Вывод в формате CSV (кавычки по RFC 4180)",
+    help_tsv: "  --tsv          Вывод с табуляцией в качестве разделителя",
+    help_delimiter: "  --delimiter <c> Разделитель полей CSV, например ';'",
+    help_import: "  --keepass-csv, --keepass-xml, --bitwarden, --1password  Форматы импорта менеджеров паролей",
+    help_name_template: "  --name-template <t>, --username <u>  Имя записи (по умолчанию passwg-{id}) и логин; {id} — номер",
+    help_hash: "  --hash <алг>   Добавить хеш: sha512crypt, bcrypt, argon2id, htpasswd; --hash-only — без пароля",
+    help_hibp: "  --hibp <файл>  Перегенерировать пароли, найденные в локальном файле Pwned Passwords (SHA-1 или NTLM)",
     help_stats: "  -s, --stats    Показать статистику скорости",
     help_fast: "  -f, --fast     Режим макс. скорости (A-Z, a-z, 0-9, _, -)",
-    help_copy: "  -c, --copy     Копировать один пароль в буфер (только Wayland)",
+    help_copy: "  -c, --copy     Копировать один пароль в буфер обмена (X11, Wayland, macOS, Windows)",
     help_rounds: "  -r, --rounds   Раунды ChaCha (8, 12, 20). По умолчанию: 8",
+    help_length_count: "  --length <n>, --count <n>  Длина и количество флагами; позиционные числа важнее. Длина из файла настроек к -w не применяется",
+    help_profile: "  --profile <имя> Взять [profile.<имя>] из ~/.config/passwg/config.toml; ключи верхнего уровня — умолчания, флаги сильнее файла. --no-fast, --no-stats, --no-copy, --no-words, --ambiguous, --plain отменяют заданные там переключатели",
+    help_charset: "  --charset <s>  Свой алфавит: диапазоны (a-zA-Z0-9@#) и классы (lower,upper,digits,symbols)",
+    help_no_ambiguous: "  --no-ambiguous Исключить похожие символы (0 O o 1 l I | ` ' \")",
+    help_require: "  --require <p>  Минимум по классам, например upper=2,digit=1,symbol=1",
+    help_policy: "  --policy <файл> Файл политики (TOML): границы длины, минимумы [require], запрещенные символы, max_repeat",
+    help_pattern: "  --pattern <m>  Маска: ?l ?u ?d ?s ?a ?h ?H, остальное литералы (первое число — количество)",
+    help_bits: "  --bits <n>     Подобрать длину (или число слов) под n бит энтропии (первое число — количество)",
+    help_wordlist: "  --wordlist <f> Словарь для -w: bip39, ru, ru-translit, eff-large, eff-short или файл (по слову в строке / diceware)",
+    help_sep: "  --sep <s>      Разделитель слов для -w (по умолчанию '-'), 'random' — из ' ._-'",
+    help_case: "  --case <c>     Регистр слов для -w: lower, title, upper, random",
+    help_append: "  --append-digit, --append-symbol  Вставить во фразу случайную цифру / символ",
+    help_seed: "  --seed <hex>   Воспроизводимый вывод для тестов. НЕБЕЗОПАСНО для настоящих паролей",
+    help_ordered: "  --ordered, --unordered  Сохранять порядок id (по умолчанию для CSV/JSON) или писать по готовности",
+    help_clear_after: "  --clear-after <сек>     С -c: очистить буфер обмена через N секунд, если там все еще пароль",
     help_h: "  -h, --help     Показать эту справку",
+    help_syntax: "Значения флагов: --флаг значение или --флаг=значение; короткие флаги склеиваются (-sf, -r20, -ofile); -- завершает флаги.\nОкружение: PASSWG_ROUNDS, PASSWG_LENGTH, PASSWG_COUNT, PASSWG_CHARSET и другие PASSWG_<ФЛАГ> сильнее файла настроек, флаги сильнее окружения",
+    help_derive: "passwg derive <сайт> [--login L] [--counter N] [длина] [флаги]\n  Вывести постоянный пароль сайта из мастер-пароля (Argon2id), без хранилища",
+    help_check: "passwg check [--hibp <файл>] [--json|--ndjson|--csv] [пароли.txt]\n  Оценить стойкость паролей из stdin или файла (в стиле zxcvbn) и проверить их по локальному файлу Pwned Passwords",
+    help_validate: "passwg validate --policy <файл> [пароли.txt]\n  Проверить пароли из stdin или файла тем же файлом политики, что и при генерации; выводит номера строк и причины",
+    derive_prompt: "Мастер-пароль: ",
     stat_title: "СТАТИСТИКА",
     stat_time: "Время выполнения:  ",
     stat_speed: "Скорость потока:   ",
     stat_perf: "Производительность: ",
+    stat_entropy: "Энтропия:           ",
+    stat_length: "длина",
 };
 
 pub fn get_locale() -> &'static I18n {
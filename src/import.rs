@@ -0,0 +1,149 @@
+use crate::writer::{OutputFormat, json_escape};
+
+/// Подстановка номера пароля в `--name-template` и `--username`
+pub const ID_PLACEHOLDER: &str = "{id}";
+
+/// Часть записи вокруг пароля: готовые байты или номер пароля
+pub enum Piece {
+    Bytes(Vec<u8>),
+    Id,
+}
+
+/// Раскладка одной записи формата импорта: что пишется до пароля и после.
+/// Шаблоны экранируются один раз на чанк, в цикле остается только копирование
+pub struct Layout {
+    pub before: Vec<Piece>,
+    pub after: Vec<u8>,
+}
+
+impl Layout {
+    /// Верхняя граница длины записи без самого пароля
+    pub fn max_len(&self) -> usize {
+        let pieces: usize = self
+            .before
+            .iter()
+            .map(|p| match p {
+                Piece::Bytes(b) => b.len(),
+                Piece::Id => 20,
+            })
+            .sum();
+        pieces + self.after.len()
+    }
+}
+
+/// Раскладка записи для форматов менеджеров паролей, для остальных — None.
+/// Первая запись Bitwarden пишется без запятой: это решает генератор по id.
+pub fn layout(format: OutputFormat, name_template: &str, username: &str) -> Option<Layout> {
+    let mut before = Vec::new();
+    let after: &[u8] = match format {
+        // Формат экспорта KeePassXC, его понимает и импорт KeePass 2
+        OutputFormat::KeepassCsv => {
+            push_bytes(&mut before, b"\"passwg\",");
+            push_template(&mut before, name_template, csv_field);
+            push_bytes(&mut before, b",");
+            push_template(&mut before, username, csv_field);
+            push_bytes(&mut before, b",");
+            b",\"\",\"\"\n"
+        }
+        // XML KeePass 1.x: KeePass 2 импортирует его без настройки колонок
+        OutputFormat::KeepassXml => {
+            push_bytes(&mut before, b"<pwentry>\n  <group>passwg</group>\n  <title>");
+            push_template(&mut before, name_template, xml_escape);
+            push_bytes(&mut before, b"</title>\n  <username>");
+            push_template(&mut before, username, xml_escape);
+            push_bytes(&mut before, b"</username>\n  <password>");
+            b"</password>\n</pwentry>\n"
+        }
+        OutputFormat::Bitwarden => {
+            push_bytes(&mut before, b"\n    {\"type\":1,\"name\":\"");
+            push_template(&mut before, name_template, json_escape);
+            push_bytes(&mut before, b"\",\"login\":{\"username\":\"");
+            push_template(&mut before, username, json_escape);
+            push_bytes(&mut before, b"\",\"password\":\"");
+            b"\"}}"
+        }
+        OutputFormat::OnePasswordCsv => {
+            push_template(&mut before, name_template, csv_field);
+            push_bytes(&mut before, b",,");
+            push_template(&mut before, username, csv_field);
+            push_bytes(&mut before, b",");
+            b",\n"
+        }
+        _ => return None,
+    };
+    Some(Layout {
+        before,
+        after: after.to_vec(),
+    })
+}
+
+/// Начало файла для форматов импорта
+pub fn header(format: OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::KeepassCsv => "\"Group\",\"Title\",\"Username\",\"Password\",\"URL\",\"Notes\"\n",
+        OutputFormat::KeepassXml => "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<pwlist>\n",
+        OutputFormat::Bitwarden => "{\n  \"encrypted\": false,\n  \"folders\": [],\n  \"items\": [",
+        OutputFormat::OnePasswordCsv => "Title,Website,Username,Password,Notes\n",
+        _ => "",
+    }
+}
+
+/// Конец файла для форматов импорта
+pub fn footer(format: OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::KeepassXml => "</pwlist>\n",
+        OutputFormat::Bitwarden => "\n  ]\n}\n",
+        _ => "",
+    }
+}
+
+/// Подставляет номер пароля в шаблон
+pub fn render(template: &str, id: u64) -> String {
+    template.replace(ID_PLACEHOLDER, &id.to_string())
+}
+
+/// Экранирует текст внутри XML-элемента
+pub fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Поле CSV по RFC 4180: в кавычках, если нужно, с удвоением `"`
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn push_bytes(pieces: &mut Vec<Piece>, bytes: &[u8]) {
+    match pieces.last_mut() {
+        Some(Piece::Bytes(last)) => last.extend_from_slice(bytes),
+        _ => pieces.push(Piece::Bytes(bytes.to_vec())),
+    }
+}
+
+/// Разбивает шаблон по `{id}`. Шаблон экранируется целиком, а не по частям,
+/// чтобы CSV-кавычки охватили все поле; номер состоит из цифр и экранирования не требует
+fn push_template(pieces: &mut Vec<Piece>, template: &str, escape: fn(&str) -> String) {
+    // Символ из области частного использования: его не трогает ни одно экранирование
+    const MARK: &str = "\u{E000}";
+    let escaped = escape(&template.replace(ID_PLACEHOLDER, MARK));
+    let mut parts = escaped.split(MARK);
+    if let Some(first) = parts.next() {
+        push_bytes(pieces, first.as_bytes());
+    }
+    for part in parts {
+        pieces.push(Piece::Id);
+        push_bytes(pieces, part.as_bytes());
+    }
+}
@@ -0,0 +1,25 @@
+//! Быстрый генератор паролей и фраз.
+//!
+//! ```no_run
+//! let mut generator = passwg::Generator::builder().length(20).build().unwrap();
+//! let password = generator.generate();
+//! println!("{}", password.expose());
+//! ```
+
+pub mod breach;
+pub mod charset;
+pub mod derive;
+pub mod generator;
+pub mod hash;
+pub mod import;
+pub mod pattern;
+pub mod phrase;
+pub mod policy;
+pub mod secret;
+pub mod strength;
+pub mod words;
+pub mod writer;
+mod avx2;
+
+pub use generator::{GenParams, Generator, GeneratorBuilder, Mode};
+pub use secret::Secret;
@@ -1,11 +1,12 @@
 mod args;
-mod generator;
+mod clipboard;
 mod i18n;
-mod words;
-mod writer;
-mod avx2;
+mod profile;
 
-use crate::writer::OutputFormat;
+use crate::i18n::I18n;
+use passwg::writer::{self, OutputFormat, ReorderBuffer};
+use passwg::secret::SecretPool;
+use passwg::{Secret, generator};
 use rayon::prelude::*;
 use std::io::Write;
 use std::sync::{Arc, Mutex};
@@ -16,8 +17,13 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 // Целевой размер данных в одном чанке — 32 КБ (чтобы влезло в L1d любого ядра)
 const TARGET_L1_SIZE: usize = 32 * 1024;
+// Паролей в чанке при --hash: bcrypt и argon2 тратят десятки миллисекунд на каждый
+const HASH_CHUNK_SIZE: u64 = generator::SEED_BLOCK;
 
 fn main() -> std::io::Result<()> {
+    // Пароли живут в памяти процесса — не даем им попасть в core dump
+    passwg::secret::disable_core_dumps();
+
     let locale = i18n::get_locale();
     let raw_args: Vec<String> = std::env::args().collect();
 
@@ -26,15 +32,86 @@ fn main() -> std::io::Result<()> {
         return Ok(());
     }
 
-    let config = args::parse_args(&raw_args);
+    if raw_args.len() > 1 && raw_args[1] == clipboard::DAEMON_ARG {
+        clipboard::run_daemon(&raw_args);
+    }
+
+    if raw_args.len() > 1 && raw_args[1] == "derive" {
+        return derive_main(&raw_args, locale);
+    }
+
+    if raw_args.len() > 1 && raw_args[1] == "check" {
+        return check_main(&raw_args, locale);
+    }
+
+    if raw_args.len() > 1 && raw_args[1] == "validate" {
+        return validate_main(&raw_args, locale);
+    }
+
+    let raw_args = match profile::apply(&raw_args) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("Ошибка: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let config = parse_or_exit(args::parse_args(&raw_args), locale);
     if config.count == 0 {
         return Ok(());
     }
 
+    let builder = config.builder();
+    let params = match builder.build_params() {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Ошибка: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if config.target_bits.is_some() {
+        eprintln!(
+            "{} {:.2} bits ({} {})",
+            locale.stat_entropy.trim_end(),
+            params.entropy_bits(),
+            locale.stat_length.trim_end(),
+            params.length
+        );
+    }
+
+    if let Some(limit) = params.hash.and_then(|h| h.truncates_at())
+        && params.max_password_len() > limit
+    {
+        eprintln!(
+            "Предупреждение: bcrypt учитывает только первые {} байт пароля, остаток не влияет на хеш",
+            limit
+        );
+    }
+
+    // Пароли отбраковываются целиком, поэтому слишком строгая политика
+    // для короткой длины означает много холостых попыток
+    if !params.policy.is_empty() {
+        let acceptance = params.policy.acceptance(params.length, params.alphabet());
+        if let Some(acceptance) = acceptance.ok().filter(|&a| a < 0.01) {
+            eprintln!(
+                "Предупреждение: политике удовлетворяет лишь {:.4}% паролей, генерация будет медленной",
+                acceptance * 100.0
+            );
+        }
+    }
+
     // АВТОКОРРЕКЦИЯ: Вычисляем размер чанка на лету
-    // Примерный размер одного пароля: длина + ID (до 20) + разделители
-    let bytes_per_pass = config.length + 20;
-    let chunk_size = (TARGET_L1_SIZE / bytes_per_pass).clamp(32, 16384) as u64;
+    // Примерный размер одного пароля: длина + ID (до 20) + разделители.
+    // Для фраз длина — число слов, а слово в UTF-8 может занимать много байт.
+    let bytes_per_pass = params.max_password_len() + 20;
+    // Хеширование на порядки медленнее генерации, поэтому с --hash чанки мелкие,
+    // иначе небольшая партия целиком попала бы в один поток
+    let chunk_size = if params.hash.is_some() {
+        HASH_CHUNK_SIZE
+    } else {
+        let size = (TARGET_L1_SIZE / bytes_per_pass).clamp(32, 16384) as u64;
+        size - size % generator::SEED_BLOCK
+    };
 
     let start_time = if config.show_stats {
         Some(Instant::now())
@@ -43,21 +120,36 @@ fn main() -> std::io::Result<()> {
     };
 
     let out = writer::get_writer(&config.out_file)?;
-    let out_arc = Arc::new(Mutex::new(out));
+    let out_arc = Arc::new(Mutex::new(ReorderBuffer::new(out)));
 
     {
         let mut out_lock = out_arc.lock().unwrap();
         match config.format {
-            OutputFormat::Csv => writeln!(out_lock, "id,password")?,
+            OutputFormat::Csv => {
+                let d = params.delimiter as char;
+                match (params.hash.is_some(), params.hash_only) {
+                    (true, true) => writeln!(out_lock, "id{}hash", d)?,
+                    (true, false) => writeln!(out_lock, "id{d}password{d}hash")?,
+                    _ => writeln!(out_lock, "id{}password", d)?,
+                }
+            }
             OutputFormat::Json => write!(out_lock, "[")?,
-            _ => {}
+            OutputFormat::JsonObjects => {
+                write!(out_lock, "{}", writer::json_objects_header(params.rounds))?
+            }
+            format => write!(out_lock, "{}", passwg::import::header(format))?,
         }
     }
 
-    let num_chunks = (config.count + chunk_size - 1) / chunk_size;
+    let num_chunks = config.count.div_ceil(chunk_size);
     let first_password = Arc::new(Mutex::new(None));
+    // По паре буферов на поток: пока один пишется, второй уже заполняется
+    let pool = SecretPool::new(
+        chunk_size as usize * params.max_record_len(),
+        2 * rayon::current_num_threads(),
+    );
 
-    (0..num_chunks).into_par_iter().for_each(|chunk_idx| {
+    let generated: Result<(), String> = (0..num_chunks).into_par_iter().try_for_each(|chunk_idx| {
         let start_id = chunk_idx * chunk_size + 1;
         let size = if chunk_idx == num_chunks - 1 {
             config.count - chunk_idx * chunk_size
@@ -65,46 +157,305 @@ fn main() -> std::io::Result<()> {
             chunk_size
         };
 
-        let data = generator::generate_chunk(
-            start_id,
-            size,
-            config.length,
-            config.fast_mode,
-            config.word_mode,
-            config.format,
-            config.rounds
-        );
-
-        if config.copy_mode && start_id == 1 {
-            let mut fp = first_password.lock().unwrap();
-            if fp.is_none() {
-                let s = String::from_utf8_lossy(&data);
-                *fp = s.lines().next().map(|l| {
-                    l.trim_matches(|c| c == ' ' || c == '"' || c == ',')
-                        .to_string()
-                });
-            }
-        }
+        // Для -c берем первый пароль до экранирования под формат вывода
+        let data = if config.copy_mode && start_id == 1 {
+            let (data, first) = generator::generate_chunk_keep_first(start_id, size, &params)?;
+            *first_password.lock().unwrap() = first;
+            data
+        } else {
+            let mut data = pool.take();
+            generator::generate_chunk_into(start_id, size, &params, &mut data)?;
+            data
+        };
 
         let mut out_lock = out_arc.lock().unwrap();
-        let _ = out_lock.write_all(&data);
+        if config.ordered {
+            let _ = out_lock.push(chunk_idx, data, |data| pool.put(data));
+        } else {
+            let _ = out_lock.write_all(&data);
+            drop(out_lock);
+            pool.put(data);
+        }
+        Ok(())
     });
+    // Ошибка базы утечек: остальные чанки rayon уже не запускает
+    if let Err(e) = generated {
+        eprintln!("Ошибка: {}", e);
+        std::process::exit(1);
+    }
 
     {
         let mut out_lock = out_arc.lock().unwrap();
-        if config.format == OutputFormat::Json {
-            write!(out_lock, "\n]")?;
+        match config.format {
+            OutputFormat::Json => write!(out_lock, "\n]")?,
+            OutputFormat::JsonObjects => write!(out_lock, "\n]}}")?,
+            format => write!(out_lock, "{}", passwg::import::footer(format))?,
         }
         let _ = out_lock.flush();
     }
 
     if let Some(pwd) = first_password.lock().unwrap().as_ref() {
-        generator::copy_to_clipboard(pwd);
+        copy_to_clipboard(pwd.expose(), config.clear_after);
     }
 
     if let Some(start) = start_time {
-        generator::print_report(start, config.count, config.length, locale);
+        print_report(start, config.count, params.entropy_bits(), locale);
+    }
+
+    Ok(())
+}
+
+/// Результат разбора аргументов; `-h` где угодно в строке печатает справку
+fn parse_or_exit<T>(parsed: Result<T, args::ArgError>, locale: &I18n) -> T {
+    match parsed {
+        Ok(value) => value,
+        Err(args::ArgError::Help) => {
+            args::print_help(locale, APP_NAME, VERSION);
+            std::process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("Ошибка: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `passwg derive <сайт> [--login L] [--counter N] [флаги генерации]`.
+/// Пароль не хранится нигде: он каждый раз выводится из мастер-пароля.
+fn derive_main(raw_args: &[String], locale: &I18n) -> std::io::Result<()> {
+    let derive = parse_or_exit(args::parse_derive(raw_args), locale);
+    let config = derive.config;
+
+    let master = match read_master(locale) {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("Ошибка: не удалось прочитать мастер-пароль: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let generator = passwg::derive::derive_seed(
+        master.expose().as_bytes(),
+        &derive.site,
+        &derive.login,
+        derive.counter,
+    )
+    .and_then(|seed| config.builder().seed(seed).build());
+    let mut generator = match generator {
+        Ok(g) => g,
+        Err(e) => {
+            eprintln!("Ошибка: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let password = generator.generate();
+    if config.copy_mode {
+        copy_to_clipboard(password.expose(), config.clear_after);
+    } else {
+        println!("{}", password.expose());
     }
+    if config.show_stats {
+        eprintln!(
+            "{} {:.2} bits",
+            locale.stat_entropy.trim_end(),
+            generator.entropy_bits()
+        );
+    }
+    Ok(())
+}
 
+/// `passwg validate --policy policy.toml [пароли.txt]`: проверка готовых паролей
+/// той же политикой, что и при генерации. Пароли не выводятся — только номера
+/// строк и причины. Код выхода 1, если хотя бы один пароль не прошел
+fn validate_main(raw_args: &[String], locale: &I18n) -> std::io::Result<()> {
+    use std::io::BufRead;
+
+    let validate = parse_or_exit(args::parse_validate(raw_args), locale);
+    let input: Box<dyn BufRead> = match &validate.input {
+        Some(path) => match std::fs::File::open(path) {
+            Ok(f) => Box::new(std::io::BufReader::new(f)),
+            Err(e) => {
+                eprintln!("Ошибка: не удалось открыть '{}': {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => Box::new(std::io::stdin().lock()),
+    };
+
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    let (mut total, mut failed) = (0u64, 0u64);
+    for (n, line) in input.lines().enumerate() {
+        let line = Secret::from(line?);
+        let password = line.expose().trim_end_matches('\r');
+        if password.is_empty() {
+            continue;
+        }
+        total += 1;
+
+        let reasons = validate.policy.violations(password);
+        if !reasons.is_empty() {
+            failed += 1;
+            writeln!(out, "строка {}: {}", n + 1, reasons.join("; "))?;
+        }
+    }
+    out.flush()?;
+
+    eprintln!("Не прошли политику: {} из {}", failed, total);
+    if failed > 0 {
+        std::process::exit(1);
+    }
     Ok(())
 }
+
+/// `passwg check [--hibp <файл>] [пароли.txt]`: оценка стойкости и офлайн-проверка
+/// по базе утечек. Код выхода 1, если хотя бы один пароль найден в утечках
+fn check_main(raw_args: &[String], locale: &I18n) -> std::io::Result<()> {
+    use passwg::import::csv_field;
+    use passwg::writer::json_escape;
+    use std::io::BufRead;
+
+    let check = parse_or_exit(args::parse_check(raw_args), locale);
+    let db = match check.hibp.as_deref().map(passwg::breach::BreachDb::open) {
+        Some(Ok(db)) => Some(db),
+        Some(Err(e)) => {
+            eprintln!("Ошибка: {}", e);
+            std::process::exit(1);
+        }
+        None => None,
+    };
+
+    let input: Box<dyn BufRead> = match &check.input {
+        Some(path) => match std::fs::File::open(path) {
+            Ok(f) => Box::new(std::io::BufReader::new(f)),
+            Err(e) => {
+                eprintln!("Ошибка: не удалось открыть '{}': {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => Box::new(std::io::stdin().lock()),
+    };
+
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    match (check.format, db.is_some()) {
+        (OutputFormat::Csv, true) => writeln!(out, "password,entropy_bits,score,patterns,pwned")?,
+        (OutputFormat::Csv, false) => writeln!(out, "password,entropy_bits,score,patterns")?,
+        (OutputFormat::Json, _) => write!(out, "[")?,
+        _ => {}
+    }
+
+    let (mut total, mut pwned) = (0u64, 0u64);
+    for line in input.lines() {
+        let line = Secret::from(line?);
+        let password = line.expose().trim_end_matches('\r');
+        if password.is_empty() {
+            continue;
+        }
+        total += 1;
+
+        let estimate = passwg::strength::estimate(password);
+        let count = match &db {
+            Some(db) => Some(db.lookup(password.as_bytes())?.unwrap_or(0)),
+            None => None,
+        };
+        if count.is_some_and(|c| c > 0) {
+            pwned += 1;
+        }
+
+        let patterns = estimate.patterns.join("+");
+        match check.format {
+            OutputFormat::Csv => {
+                write!(
+                    out,
+                    "{},{:.2},{},{}",
+                    csv_field(password),
+                    estimate.bits,
+                    estimate.score,
+                    patterns
+                )?;
+                match count {
+                    Some(c) => writeln!(out, ",{}", c)?,
+                    None => writeln!(out)?,
+                }
+            }
+            OutputFormat::Json | OutputFormat::Ndjson => {
+                if check.format == OutputFormat::Json {
+                    write!(out, "{}\n  ", if total == 1 { "" } else { "," })?;
+                }
+                let patterns: Vec<String> =
+                    estimate.patterns.iter().map(|p| format!("\"{}\"", p)).collect();
+                write!(
+                    out,
+                    "{{\"password\":\"{}\",\"entropy_bits\":{:.2},\"score\":{},\"patterns\":[{}]",
+                    json_escape(password),
+                    estimate.bits,
+                    estimate.score,
+                    patterns.join(",")
+                )?;
+                if let Some(c) = count {
+                    write!(out, ",\"pwned\":{}", c)?;
+                }
+                write!(out, "}}")?;
+                if check.format == OutputFormat::Ndjson {
+                    writeln!(out)?;
+                }
+            }
+            _ => {
+                write!(
+                    out,
+                    "{}\tentropy={:.2}\tscore={}\tpatterns={}",
+                    password, estimate.bits, estimate.score, patterns
+                )?;
+                match count {
+                    Some(c) => writeln!(out, "\tpwned={}", c)?,
+                    None => writeln!(out)?,
+                }
+            }
+        }
+    }
+    if check.format == OutputFormat::Json {
+        writeln!(out, "\n]")?;
+    }
+    out.flush()?;
+
+    if db.is_some() {
+        eprintln!("Найдено в утечках: {} из {}", pwned, total);
+    }
+    if pwned > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// С терминала читаем без эха, из пайпа — первую строку
+fn read_master(locale: &I18n) -> std::io::Result<Secret> {
+    use std::io::IsTerminal;
+    if std::io::stdin().is_terminal() {
+        rpassword::prompt_password(locale.derive_prompt).map(Secret::from)
+    } else {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        let len = line.trim_end_matches(['\r', '\n']).len();
+        line.truncate(len);
+        Ok(Secret::from(line))
+    }
+}
+
+fn copy_to_clipboard(pwd: &str, clear_after: Option<u64>) {
+    if let Err(e) = clipboard::copy(pwd, clear_after) {
+        eprintln!("Ошибка: буфер обмена недоступен: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn print_report(start: Instant, count: u64, entropy_bits: f64, l: &I18n) {
+    let dur = start.elapsed().as_secs_f64();
+    if dur > 0.0 {
+        let speed = count as f64 / dur;
+        eprintln!("\n--- {} ---", l.stat_title);
+        eprintln!("{}: {:.4} s", l.stat_time, dur);
+        eprintln!("{}: {:.2} p/s", l.stat_speed, speed);
+        eprintln!("{}: {:.2} Mp/s", l.stat_perf, speed / 1_000_000.0);
+        eprintln!("{}: {:.2} bits", l.stat_entropy, entropy_bits);
+    }
+}
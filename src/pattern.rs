@@ -0,0 +1,164 @@
+use crate::charset::{Charset, DIGITS, LOWER, SYMBOLS, UPPER};
+use crate::generator::CHARSET;
+
+/// Одна позиция шаблона: фиксированный символ или класс для случайного выбора
+#[derive(Clone)]
+pub enum Slot {
+    Literal(u8),
+    Class(Charset),
+}
+
+/// Шаблон в стиле масок hashcat: `?u?l?l?l-?d?d?d?d-?s`.
+#[derive(Clone)]
+pub struct Pattern {
+    pub slots: Vec<Slot>,
+}
+
+impl Pattern {
+    /// Поддерживаемые классы:
+    /// ?l — a-z, ?u — A-Z, ?d — 0-9, ?s — спецсимволы, ?a — весь CHARSET,
+    /// ?h — 0-9a-f, ?H — 0-9A-F, ?? — сам символ '?'. Остальное — литералы.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut slots = Vec::new();
+        let mut bytes = spec.bytes();
+
+        while let Some(b) = bytes.next() {
+            if !(0x20..=0x7E).contains(&b) {
+                return Err(format!(
+                    "недопустимый символ в шаблоне '{}': разрешены только печатные ASCII",
+                    spec
+                ));
+            }
+            if b != b'?' {
+                slots.push(Slot::Literal(b));
+                continue;
+            }
+            let class: &[u8] = match bytes.next() {
+                Some(b'l') => LOWER,
+                Some(b'u') => UPPER,
+                Some(b'd') => DIGITS,
+                Some(b's') => SYMBOLS,
+                Some(b'a') => CHARSET,
+                Some(b'h') => b"0123456789abcdef",
+                Some(b'H') => b"0123456789ABCDEF",
+                Some(b'?') => {
+                    slots.push(Slot::Literal(b'?'));
+                    continue;
+                }
+                Some(other) => {
+                    return Err(format!(
+                        "неизвестный класс '?{}' в шаблоне. Допустимо: ?l ?u ?d ?s ?a ?h ?H ??",
+                        other as char
+                    ));
+                }
+                None => return Err("шаблон не может заканчиваться одиночным '?'".into()),
+            };
+            slots.push(Slot::Class(Charset::known(class.to_vec())));
+        }
+
+        if !slots.iter().any(|s| matches!(s, Slot::Class(_))) {
+            return Err(format!("шаблон '{}' не содержит ни одного класса", spec));
+        }
+        Ok(Pattern { slots })
+    }
+
+    /// Убирает символы из каждого класса (для --no-ambiguous)
+    pub fn without(&self, excluded: &[u8]) -> Result<Self, String> {
+        let mut slots = Vec::with_capacity(self.slots.len());
+        for slot in &self.slots {
+            slots.push(match slot {
+                Slot::Literal(b) => Slot::Literal(*b),
+                Slot::Class(c) => Slot::Class(c.without(excluded)?),
+            });
+        }
+        Ok(Pattern { slots })
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Энтропия: сумма log2 размеров классов, литералы ее не добавляют
+    pub fn entropy_bits(&self) -> f64 {
+        self.slots
+            .iter()
+            .map(|s| match s {
+                Slot::Literal(_) => 0.0,
+                Slot::Class(c) => (c.symbols.len() as f64).log2(),
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{Generator, Mode};
+
+    fn classes(pattern: &Pattern) -> Vec<Option<&[u8]>> {
+        pattern
+            .slots
+            .iter()
+            .map(|s| match s {
+                Slot::Literal(_) => None,
+                Slot::Class(c) => Some(&c.symbols[..]),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn masks_and_literals() {
+        let pattern = Pattern::parse("?u?l?d?s").unwrap();
+        assert_eq!(classes(&pattern), [Some(UPPER), Some(LOWER), Some(DIGITS), Some(SYMBOLS)]);
+
+        let pattern = Pattern::parse("id-?h?H").unwrap();
+        assert_eq!(pattern.len(), 5);
+        assert!(matches!(pattern.slots[..3], [Slot::Literal(b'i'), Slot::Literal(b'd'), Slot::Literal(b'-')]));
+        assert_eq!(classes(&pattern)[3..], [Some(&b"0123456789abcdef"[..]), Some(&b"0123456789ABCDEF"[..])]);
+
+        // `??` — сам знак вопроса, а не начало класса
+        let pattern = Pattern::parse("???d").unwrap();
+        assert!(matches!(pattern.slots[0], Slot::Literal(b'?')));
+        assert_eq!(classes(&pattern)[1], Some(DIGITS));
+
+        let bits = Pattern::parse("?d?d-?a").unwrap().entropy_bits();
+        assert!((bits - (2.0 * 10f64.log2() + (CHARSET.len() as f64).log2())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn template_shape() {
+        // Шаблон вида Xxxx-9999
+        let pattern = Pattern::parse("?u?l?l?l-?d?d?d?d").unwrap();
+        let generator = Generator::builder().mode(Mode::Pattern(pattern)).seed([3; 32]).build().unwrap();
+        for password in generator.take(100) {
+            let p = password.expose().as_bytes();
+            assert_eq!(p.len(), 9);
+            assert!(p[0].is_ascii_uppercase());
+            assert!(p[1..4].iter().all(u8::is_ascii_lowercase));
+            assert_eq!(p[4], b'-');
+            assert!(p[5..].iter().all(u8::is_ascii_digit));
+        }
+    }
+
+    #[test]
+    fn errors() {
+        for (spec, needle) in [
+            ("?x", "'?x'"),
+            ("?d?", "одиночным '?'"),
+            ("abc", "ни одного класса"),
+            ("??", "ни одного класса"),
+            ("", "ни одного класса"),
+            ("é?d", "печатные ASCII"),
+            ("?d\t?d", "печатные ASCII"),
+        ] {
+            let error = Pattern::parse(spec).err().unwrap();
+            assert!(error.contains(needle), "{}: {}", spec, error);
+        }
+        // Класс, от которого ничего не осталось после исключения символов
+        assert!(Pattern::parse("?d").unwrap().without(b"0123456789").is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::words::Wordlist;
+use std::borrow::Cow;
+
+/// Разделители, из которых выбирает `--sep random`
+pub const RANDOM_SEPARATORS: &[u8] = b" ._-";
+/// Символы для `--append-symbol`. Без разделителей, иначе разные позиции
+/// вставки могли бы дать одинаковую строку
+pub const APPEND_SYMBOLS: &[u8] = b"!#$%&*+=?@^~";
+
+#[derive(Clone)]
+pub enum Separator {
+    Fixed(String),
+    Random,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Case {
+    Lower,
+    Title,
+    Upper,
+    Random,
+}
+
+/// Оформление фраз в режиме слов
+#[derive(Clone)]
+pub struct PhraseStyle {
+    pub sep: Separator,
+    pub case: Case,
+    pub append_digit: bool,
+    pub append_symbol: bool,
+}
+
+impl Default for PhraseStyle {
+    fn default() -> Self {
+        PhraseStyle {
+            sep: Separator::Fixed("-".into()),
+            case: Case::Lower,
+            append_digit: false,
+            append_symbol: false,
+        }
+    }
+}
+
+impl Separator {
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "random" => Separator::Random,
+            other => Separator::Fixed(other.to_string()),
+        }
+    }
+
+    /// Максимальная длина разделителя в байтах
+    pub fn max_len(&self) -> usize {
+        match self {
+            Separator::Fixed(s) => s.len(),
+            Separator::Random => 1,
+        }
+    }
+}
+
+impl Case {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "lower" => Ok(Case::Lower),
+            "title" => Ok(Case::Title),
+            "upper" => Ok(Case::Upper),
+            "random" => Ok(Case::Random),
+            other => Err(format!(
+                "неизвестный регистр '{}'. Допустимо: lower, title, upper, random",
+                other
+            )),
+        }
+    }
+
+    /// Варианты словаря, из которых выбирается слово.
+    /// Считаются один раз, чтобы не менять регистр на каждом пароле.
+    pub fn variants(self, list: &Wordlist) -> Vec<Wordlist> {
+        match self {
+            Case::Lower => vec![list.clone()],
+            Case::Title => vec![map_words(list, title_case)],
+            Case::Upper => vec![map_words(list, |w| w.to_uppercase())],
+            Case::Random => vec![
+                list.clone(),
+                map_words(list, title_case),
+                map_words(list, |w| w.to_uppercase()),
+            ],
+        }
+    }
+}
+
+impl PhraseStyle {
+    /// Энтропия, добавляемая оформлением к фразе из `words` слов
+    pub fn extra_bits(&self, words: usize) -> f64 {
+        let mut bits = 0.0;
+        if matches!(self.sep, Separator::Random) && words > 1 {
+            bits += (RANDOM_SEPARATORS.len() as f64).log2() * (words - 1) as f64;
+        }
+        if self.case == Case::Random {
+            bits += 3f64.log2() * words as f64;
+        }
+        // Вставка возможна перед любым словом или в конце фразы
+        let positions = (words + 1) as f64;
+        if self.append_digit {
+            bits += 10f64.log2() + positions.log2();
+        }
+        if self.append_symbol {
+            bits += (APPEND_SYMBOLS.len() as f64).log2() + positions.log2();
+        }
+        bits
+    }
+}
+
+fn map_words(list: &Wordlist, f: impl Fn(&str) -> String) -> Wordlist {
+    Wordlist {
+        words: list.words.iter().map(|w| Cow::Owned(f(w))).collect(),
+    }
+}
+
+/// Первая буква заглавная. Работает и для кириллицы
+fn title_case(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
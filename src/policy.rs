@@ -0,0 +1,528 @@
+use serde::Deserialize;
+
+/// Классы символов, для которых можно задать минимум
+const CLASSES: [&str; 4] = ["lower", "upper", "digit", "symbol"];
+
+/// Предел минимума для одного класса
+const MAX_CLASS_MIN: usize = 1024;
+
+/// Предел размера таблицы в `acceptance`: произведение (минимум + 1) по классам
+const MAX_STATES: usize = 1 << 12;
+
+/// Если политике удовлетворяет меньшая доля паролей, генерация отказывается
+/// работать: отбраковка целых паролей растянулась бы на миллионы попыток
+pub(crate) const MIN_ACCEPTANCE: f64 = 1e-6;
+
+/// Политика сложности: минимальное количество символов каждого класса
+/// (индексы совпадают с `CLASSES`), границы длины, запрещенные символы
+/// и предельная длина серии одинаковых символов подряд.
+/// Одна и та же политика применяется и при генерации, и в `passwg validate`.
+#[derive(Clone, Default)]
+pub struct Policy {
+    pub min: [usize; 4],
+    pub min_length: usize,
+    pub max_length: Option<usize>,
+    pub forbidden: Vec<char>,
+    pub max_repeat: Option<usize>,
+}
+
+/// Файл политики:
+///
+/// ```toml
+/// min_length = 12
+/// max_length = 64
+/// forbidden = " \"'\\"
+/// max_repeat = 2
+///
+/// [require]
+/// upper = 1
+/// digit = 1
+/// symbol = 1
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default)]
+    min_length: usize,
+    max_length: Option<usize>,
+    #[serde(default)]
+    forbidden: String,
+    max_repeat: Option<usize>,
+    #[serde(default)]
+    require: Require,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct Require {
+    #[serde(default)]
+    lower: usize,
+    #[serde(default)]
+    upper: usize,
+    #[serde(default, alias = "digits")]
+    digit: usize,
+    #[serde(default, alias = "symbols")]
+    symbol: usize,
+}
+
+/// Что нашлось в пароле при одном проходе: общий код для генерации и проверки
+struct Scan {
+    len: usize,
+    counts: [usize; 4],
+    longest_run: usize,
+    forbidden: Vec<char>,
+}
+
+/// Не-ASCII буквы (кириллица) считаются по регистру, прочее — символами.
+/// Генератор выдает только ASCII, для него это то же самое, что проверка байтов
+fn class_of(c: char) -> usize {
+    if c.is_lowercase() {
+        0
+    } else if c.is_uppercase() {
+        1
+    } else if c.is_ascii_digit() {
+        2
+    } else {
+        3
+    }
+}
+
+impl Policy {
+    /// Разбирает описание вида `upper=2,digit=1,symbol=1`.
+    /// Допускаются и множественные формы: `digits`, `symbols`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut policy = Policy::default();
+
+        for part in spec.split(',').filter(|p| !p.is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("ожидается класс=число, получено '{}'", part))?;
+            let idx = match name.trim() {
+                "lower" => 0,
+                "upper" => 1,
+                "digit" | "digits" => 2,
+                "symbol" | "symbols" => 3,
+                other => {
+                    return Err(format!(
+                        "неизвестный класс '{}'. Допустимо: {}",
+                        other,
+                        CLASSES.join(", ")
+                    ));
+                }
+            };
+            policy.min[idx] = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("неверное число в '{}'", part))?;
+        }
+        policy.check_minimums()?;
+        Ok(policy)
+    }
+
+    /// Читает файл политики в формате TOML (см. `PolicyFile`)
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("не удалось открыть '{}': {}", path, e))?;
+        let file: PolicyFile =
+            toml::from_str(&text).map_err(|e| format!("ошибка в файле политики '{}': {}", path, e))?;
+
+        if file.max_length.is_some_and(|max| max < file.min_length) {
+            return Err(format!("в '{}' max_length меньше min_length", path));
+        }
+        if file.max_repeat == Some(0) {
+            return Err(format!("в '{}' max_repeat должен быть не меньше 1", path));
+        }
+        let mut forbidden: Vec<char> = file.forbidden.chars().collect();
+        forbidden.sort_unstable();
+        forbidden.dedup();
+
+        let r = file.require;
+        let policy = Policy {
+            min: [r.lower, r.upper, r.digit, r.symbol],
+            min_length: file.min_length,
+            max_length: file.max_length,
+            forbidden,
+            max_repeat: file.max_repeat,
+        };
+        policy
+            .check_minimums()
+            .map_err(|e| format!("в '{}' {}", path, e))?;
+        Ok(policy)
+    }
+
+    /// Ограничивает минимумы по классам, иначе таблица в `acceptance`
+    /// (произведение минимумов) не поместится в память
+    fn check_minimums(&self) -> Result<(), String> {
+        for (idx, name) in CLASSES.iter().enumerate() {
+            if self.min[idx] > MAX_CLASS_MIN {
+                return Err(format!(
+                    "минимум для класса '{}' больше {}",
+                    name, MAX_CLASS_MIN
+                ));
+            }
+        }
+        let states: usize = self.min.iter().map(|&m| m + 1).product();
+        if states > MAX_STATES {
+            return Err(format!(
+                "слишком много требований к классам одновременно: произведение (минимум + 1) равно {}, допустимо не больше {}",
+                states, MAX_STATES
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.iter().all(|&m| m == 0)
+            && self.min_length == 0
+            && self.max_length.is_none()
+            && self.forbidden.is_empty()
+            && self.max_repeat.is_none()
+    }
+
+    /// Запрещенные символы, которые могут попасть в ASCII-алфавит генератора
+    pub fn forbidden_bytes(&self) -> Vec<u8> {
+        self.forbidden
+            .iter()
+            .filter(|c| c.is_ascii())
+            .map(|&c| c as u8)
+            .collect()
+    }
+
+    fn scan(&self, chars: impl Iterator<Item = char>) -> Scan {
+        let mut scan = Scan {
+            len: 0,
+            counts: [0; 4],
+            longest_run: 0,
+            forbidden: Vec::new(),
+        };
+        let mut prev = None;
+        let mut run = 0;
+        for c in chars {
+            scan.len += 1;
+            scan.counts[class_of(c)] += 1;
+            run = if prev == Some(c) { run + 1 } else { 1 };
+            scan.longest_run = scan.longest_run.max(run);
+            prev = Some(c);
+            if self.forbidden.contains(&c) && !scan.forbidden.contains(&c) {
+                scan.forbidden.push(c);
+            }
+        }
+        scan
+    }
+
+    /// Проверяет готовый пароль
+    pub fn check(&self, password: &[u8]) -> bool {
+        let scan = self.scan(password.iter().map(|&b| b as char));
+        scan.len >= self.min_length
+            && self.max_length.is_none_or(|max| scan.len <= max)
+            && scan.forbidden.is_empty()
+            && self.max_repeat.is_none_or(|max| scan.longest_run <= max)
+            && scan.counts.iter().zip(self.min.iter()).all(|(c, m)| c >= m)
+    }
+
+    /// Все нарушения политики в пароле, пустой список — пароль подходит
+    pub fn violations(&self, password: &str) -> Vec<String> {
+        let scan = self.scan(password.chars());
+        let mut out = Vec::new();
+        if scan.len < self.min_length {
+            out.push(format!("короче {} символов", self.min_length));
+        }
+        if let Some(max) = self.max_length.filter(|&max| scan.len > max) {
+            out.push(format!("длиннее {} символов", max));
+        }
+        for (idx, name) in CLASSES.iter().enumerate() {
+            if scan.counts[idx] < self.min[idx] {
+                out.push(format!(
+                    "символов класса '{}' {}, нужно не меньше {}",
+                    name, scan.counts[idx], self.min[idx]
+                ));
+            }
+        }
+        if !scan.forbidden.is_empty() {
+            let list: Vec<String> = scan.forbidden.iter().map(|c| format!("{:?}", c)).collect();
+            out.push(format!("запрещенные символы: {}", list.join(" ")));
+        }
+        if let Some(max) = self.max_repeat.filter(|&max| scan.longest_run > max) {
+            out.push(format!(
+                "одинаковых символов подряд: {}, допустимо не больше {}",
+                scan.longest_run, max
+            ));
+        }
+        out
+    }
+
+    /// Проверяет заранее, что политику вообще можно выполнить
+    pub fn validate(&self, length: usize, alphabet: &[u8]) -> Result<(), String> {
+        if length < self.min_length {
+            return Err(format!(
+                "политика требует длину не меньше {}, а длина пароля {}",
+                self.min_length, length
+            ));
+        }
+        if let Some(max) = self.max_length.filter(|&max| length > max) {
+            return Err(format!(
+                "политика допускает длину не больше {}, а длина пароля {}",
+                max, length
+            ));
+        }
+        let total: usize = self.min.iter().sum();
+        if total > length {
+            return Err(format!(
+                "политика требует минимум {} символов, а длина пароля {}",
+                total, length
+            ));
+        }
+        self.validate_alphabet(alphabet)?;
+        let acceptance = self.acceptance(length, alphabet)?;
+        if acceptance < MIN_ACCEPTANCE {
+            return Err(format!(
+                "политике удовлетворяет лишь {:.1e} паролей длины {}: увеличьте длину или ослабьте политику",
+                acceptance, length
+            ));
+        }
+        Ok(())
+    }
+
+    /// Проверяет, что в алфавите есть все классы, которых требует политика
+    pub fn validate_alphabet(&self, alphabet: &[u8]) -> Result<(), String> {
+        let sizes = class_sizes(alphabet);
+        for (idx, name) in CLASSES.iter().enumerate() {
+            if self.min[idx] > 0 && sizes[idx] == 0 {
+                return Err(format!(
+                    "политика требует класс '{}', но в алфавите нет таких символов",
+                    name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Вероятность того, что случайный пароль из `alphabet` длины `length`
+    /// удовлетворяет политике. Нужна для подсчета реальной энтропии:
+    /// отбраковка целых паролей сохраняет равномерность, но сужает пространство.
+    /// Длина и алфавит к этому моменту уже согласованы с политикой, а `max_repeat`
+    /// не учитывается: серия длиннее k отбраковывается с вероятностью порядка
+    /// длина/алфавит^k, и поправка к энтропии мала.
+    pub fn acceptance(&self, length: usize, alphabet: &[u8]) -> Result<f64, String> {
+        if self.min.iter().all(|&m| m == 0) {
+            return Ok(1.0);
+        }
+        self.check_minimums()?;
+        let sizes = class_sizes(alphabet);
+        let n = alphabet.len() as f64;
+
+        // Состояние — счетчики по классам, обрезанные сверху минимумом.
+        // Кодируем их в одно число со смешанным основанием (min + 1).
+        let radix: Vec<usize> = self.min.iter().map(|&m| m + 1).collect();
+        let states: usize = radix.iter().product();
+        let mut dp = vec![0.0f64; states];
+        dp[0] = 1.0;
+
+        for _ in 0..length {
+            let mut next = vec![0.0f64; states];
+            for (state, &p) in dp.iter().enumerate() {
+                if p == 0.0 {
+                    continue;
+                }
+                let mut rest = state;
+                let mut stride = 1;
+                for class in 0..4 {
+                    let count = rest % radix[class];
+                    rest /= radix[class];
+                    if sizes[class] > 0 {
+                        let to = if count < self.min[class] {
+                            state + stride
+                        } else {
+                            state
+                        };
+                        next[to] += p * sizes[class] as f64 / n;
+                    }
+                    stride *= radix[class];
+                }
+            }
+            dp = next;
+        }
+        Ok(dp[states - 1])
+    }
+}
+
+fn class_sizes(alphabet: &[u8]) -> [usize; 4] {
+    let mut sizes = [0usize; 4];
+    for &b in alphabet {
+        sizes[class_of(b as char)] += 1;
+    }
+    sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acceptance_matches_counting() {
+        // Из алфавита `aA` длины 2 подходят aA, Aa, AA
+        let upper = Policy::parse("upper=1").unwrap();
+        assert_eq!(upper.acceptance(2, b"aA").unwrap(), 0.75);
+        // Оба класса сразу: только aA и Aa
+        let both = Policy::parse("lower=1,upper=1").unwrap();
+        assert_eq!(both.acceptance(2, b"aA").unwrap(), 0.5);
+        assert_eq!(both.acceptance(1, b"aA").unwrap(), 0.0);
+        assert_eq!(Policy::default().acceptance(8, b"ab").unwrap(), 1.0);
+
+        // Сверка с перебором: цифра и буква в пароле длины 3 из `ab01`
+        let policy = Policy::parse("lower=1,digit=1").unwrap();
+        let alphabet = b"ab01";
+        let mut good = 0;
+        for i in 0..64 {
+            let password = [alphabet[i % 4], alphabet[i / 4 % 4], alphabet[i / 16]];
+            good += policy.check(&password) as usize;
+        }
+        assert_eq!(policy.acceptance(3, alphabet).unwrap(), good as f64 / 64.0);
+    }
+
+    #[test]
+    fn check_counts_classes() {
+        let policy = Policy::parse("upper=2,digits=1,symbol=1").unwrap();
+        assert!(policy.check(b"AB1!"));
+        assert!(policy.check(b"xAyB1!z"));
+        assert!(!policy.check(b"Ab1!"));
+        assert!(!policy.check(b"AB1x"));
+        assert!(Policy::default().check(b""));
+    }
+
+    #[test]
+    fn validate_checks_length_and_alphabet() {
+        let policy = Policy::parse("upper=2,digit=2").unwrap();
+        let alphabet = b"abcdefABCDEF0123456789";
+        assert!(policy.validate(8, alphabet).is_ok());
+        let error = policy.validate(3, alphabet).unwrap_err();
+        assert!(error.contains("минимум 4"), "{}", error);
+        let error = policy.validate(8, b"abcdef0123").unwrap_err();
+        assert!(error.contains("'upper'"), "{}", error);
+
+        let bounded = Policy {
+            min_length: 10,
+            max_length: Some(12),
+            ..Policy::default()
+        };
+        assert!(bounded.validate(9, alphabet).is_err());
+        assert!(bounded.validate(10, alphabet).is_ok());
+        assert!(bounded.validate(13, alphabet).is_err());
+    }
+
+    #[test]
+    fn impossible_policies_are_errors() {
+        // 12 цифр из 64 символов: подходит один пароль из 5 миллиардов
+        let policy = Policy::parse("digit=12").unwrap();
+        let error = policy.validate(12, crate::generator::CHARSET_FAST).unwrap_err();
+        assert!(error.contains("ослабьте политику"), "{}", error);
+
+        // Огромные минимумы не должны доходить до выделения таблицы
+        let error = Policy::parse("upper=2000").err().unwrap();
+        assert!(error.contains("'upper'"), "{}", error);
+        let error = Policy::parse("lower=100,upper=100").err().unwrap();
+        assert!(error.contains("произведение"), "{}", error);
+        let huge = Policy {
+            min: [2000, 2000, 2000, 2000],
+            ..Policy::default()
+        };
+        assert!(huge.acceptance(8000, b"aA0!").is_err());
+        assert!(huge.validate(8000, b"aA0!").is_err());
+    }
+
+    /// Пишет файл политики во временный каталог
+    fn policy_file(tag: &str, text: &str) -> String {
+        let path = std::env::temp_dir().join(format!("passwg-policy-{}-{}.toml", tag, std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        path.display().to_string()
+    }
+
+    #[test]
+    fn load_reads_every_field() {
+        let path = policy_file(
+            "full",
+            "min_length = 12\nmax_length = 64\nforbidden = \" \\\"'\"\nmax_repeat = 2\n\n[require]\nupper = 1\ndigits = 2\nsymbol = 1\n",
+        );
+        let policy = Policy::load(&path).unwrap();
+        assert_eq!(policy.min, [0, 1, 2, 1]);
+        assert_eq!((policy.min_length, policy.max_length, policy.max_repeat), (12, Some(64), Some(2)));
+        assert_eq!(policy.forbidden, [' ', '"', '\'']);
+        let _ = std::fs::remove_file(&path);
+
+        let path = policy_file("empty", "");
+        assert!(Policy::load(&path).unwrap().is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn load_rejects_bad_files() {
+        for (tag, text, needle) in [
+            ("unknown", "min_lenght = 3\n", "min_lenght"),
+            ("class", "[require]\ncyrillic = 1\n", "cyrillic"),
+            ("bounds", "min_length = 10\nmax_length = 8\n", "max_length меньше min_length"),
+            ("repeat", "max_repeat = 0\n", "max_repeat"),
+            ("huge", "[require]\nupper = 5000\n", "'upper'"),
+            ("syntax", "min_length = \n", "ошибка в файле политики"),
+        ] {
+            let path = policy_file(tag, text);
+            let error = Policy::load(&path).err().unwrap();
+            assert!(error.contains(needle), "{}: {}", tag, error);
+            let _ = std::fs::remove_file(&path);
+        }
+        assert!(Policy::load("/nonexistent/policy.toml").err().unwrap().contains("не удалось открыть"));
+    }
+
+    #[test]
+    fn violations_give_every_reason() {
+        let policy = Policy {
+            min: [0, 1, 1, 0],
+            min_length: 8,
+            max_length: Some(10),
+            forbidden: vec![' ', 'ж'],
+            max_repeat: Some(2),
+        };
+        assert!(policy.violations("Abcdef12").is_empty());
+        assert!(policy.check(b"Abcdef12"));
+
+        let reasons = policy.violations("aaa ж");
+        assert_eq!(reasons.len(), 5, "{:?}", reasons);
+        assert!(reasons[0].contains("короче 8"));
+        assert!(reasons[1].contains("'upper' 0"));
+        assert!(reasons[2].contains("'digit' 0"));
+        assert!(reasons[3].contains("' '") && reasons[3].contains("'ж'"));
+        assert!(reasons[4].contains("подряд: 3"));
+
+        assert_eq!(policy.violations("Abcdef12345"), ["длиннее 10 символов"]);
+        // Кириллица считается по регистру, как и латиница
+        assert!(policy.violations("Жабcdef1").is_empty());
+    }
+
+    #[test]
+    fn max_run_counts_identical_neighbours() {
+        let policy = Policy {
+            max_repeat: Some(2),
+            ..Policy::default()
+        };
+        assert!(policy.check(b"aabbaabb"));
+        assert!(!policy.check(b"abbba"));
+        assert!(!policy.check(b"zzz"));
+        // Повторы не подряд серией не считаются
+        assert!(policy.check(b"ababab"));
+    }
+
+    #[test]
+    fn forbidden_characters_never_generated() {
+        let policy = Policy {
+            forbidden: "aeiouAEIOU0".chars().collect(),
+            ..Policy::default()
+        };
+        assert!(!policy.check(b"xyz0"));
+        assert_eq!(policy.forbidden_bytes(), b"aeiouAEIOU0");
+
+        let generator = crate::Generator::builder().policy(policy.clone()).length(40).seed([5; 32]).build().unwrap();
+        assert!(!generator.params().alphabet().iter().any(|b| b"aeiouAEIOU0".contains(b)));
+        for password in generator.take(50) {
+            assert!(policy.check(password.expose().as_bytes()), "{:?}", password);
+        }
+    }
+}
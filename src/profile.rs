@@ -0,0 +1,170 @@
+use crate::args::{CONFIG_END, OPTIONS, SWITCHES};
+use std::path::PathBuf;
+use toml::{Table, Value};
+
+/// Флаги, которые можно задать переменной окружения `PASSWG_<ИМЯ>`:
+/// `PASSWG_ROUNDS=20` работает как `--rounds=20`. `--seed` сюда намеренно
+/// не входит: незаметный сид в окружении сделал бы все пароли предсказуемыми
+const ENV_FLAGS: [&str; 13] = [
+    "rounds", "length", "count", "charset", "require", "policy", "pattern", "bits", "wordlist", "sep", "case",
+    "hash", "hibp",
+];
+
+/// `$XDG_CONFIG_HOME/passwg/config.toml`, иначе `~/.config/passwg/config.toml`
+/// (на Windows — `%APPDATA%\passwg\config.toml`)
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(dir).join("passwg").join("config.toml"));
+    }
+    if cfg!(windows)
+        && let Some(dir) = std::env::var_os("APPDATA")
+    {
+        return Some(PathBuf::from(dir).join("passwg").join("config.toml"));
+    }
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config/passwg/config.toml"))
+}
+
+/// Подставляет настройки перед аргументами командной строки: сначала ключи
+/// верхнего уровня файла (умолчания для каждого запуска), затем таблицу
+/// `[profile.ИМЯ]`, выбранную `--profile ИМЯ` или `PASSWG_PROFILE`, затем
+/// переменные окружения. Разбор аргументов берет последнее значение флага,
+/// поэтому командная строка сильнее окружения, а окружение сильнее файла.
+/// Ключ `length = 32` превращается в `--length=32`, `fast = true` — в `--fast`,
+/// `fast = false` — в `--no-fast`. После настроек ставится `CONFIG_END`.
+pub fn apply(args: &[String]) -> Result<Vec<String>, String> {
+    let mut rest = Vec::new();
+    let mut profile = None;
+    let mut i = 1;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        if arg == "--" {
+            rest.extend_from_slice(&args[i..]);
+            break;
+        }
+        if let Some(name) = arg.strip_prefix("--profile=") {
+            profile = Some(name.to_string());
+        } else if arg == "--profile" {
+            let name = args.get(i + 1).ok_or("флаг --profile требует имени профиля")?;
+            profile = Some(name.clone());
+            i += 1;
+        } else {
+            rest.push(arg.to_string());
+        }
+        i += 1;
+    }
+    let profile = profile.or_else(|| std::env::var("PASSWG_PROFILE").ok().filter(|p| !p.is_empty()));
+
+    let mut tokens = vec![args[0].clone()];
+    push_file(profile, &mut tokens)?;
+    push_env(&mut tokens)?;
+    if tokens.len() > 1 {
+        tokens.push(CONFIG_END.to_string());
+    }
+    tokens.extend(rest);
+    Ok(tokens)
+}
+
+fn push_file(profile: Option<String>, tokens: &mut Vec<String>) -> Result<(), String> {
+    let path = config_path();
+    let Some(path) = path.as_ref().filter(|p| p.exists()) else {
+        return match profile {
+            Some(name) => Err(format!(
+                "задан профиль '{}', но файла настроек нет: {}",
+                name,
+                path.map_or("не задан HOME".into(), |p| p.display().to_string())
+            )),
+            None => Ok(()),
+        };
+    };
+    let shown = path.display().to_string();
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("не удалось прочитать '{}': {}", shown, e))?;
+    let mut table: Table = text
+        .parse()
+        .map_err(|e| format!("ошибка в файле настроек '{}': {}", shown, e))?;
+
+    let profiles = match table.remove("profile") {
+        Some(Value::Table(t)) => t,
+        None => Table::new(),
+        Some(_) => return Err(format!("в '{}' profile должен быть таблицей [profile.ИМЯ]", shown)),
+    };
+
+    push_flags(&table, &shown, tokens)?;
+    if let Some(name) = profile {
+        match profiles.get(&name) {
+            Some(Value::Table(t)) => push_flags(t, &format!("{} [profile.{}]", shown, name), tokens)?,
+            _ => {
+                let known: Vec<&str> = profiles.keys().map(|k| k.as_str()).collect();
+                return Err(format!(
+                    "профиль '{}' не найден в '{}'. Доступны: {}",
+                    name,
+                    shown,
+                    if known.is_empty() { "нет профилей".into() } else { known.join(", ") }
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn push_env(tokens: &mut Vec<String>) -> Result<(), String> {
+    for (name, value) in std::env::vars_os() {
+        let Some(flag) = name.to_str().and_then(|n| n.strip_prefix("PASSWG_")) else {
+            continue;
+        };
+        let flag = flag.to_ascii_lowercase().replace('_', "-");
+        if flag == "profile" {
+            continue;
+        }
+        if !ENV_FLAGS.contains(&flag.as_str()) {
+            eprintln!(
+                "Предупреждение: переменная PASSWG_{} не поддерживается. Допустимо: {}",
+                flag.to_ascii_uppercase().replace('-', "_"),
+                ENV_FLAGS.map(|f| format!("PASSWG_{}", f.to_ascii_uppercase())).join(", ")
+            );
+            continue;
+        }
+        let value = value
+            .into_string()
+            .map_err(|_| format!("переменная PASSWG_{} содержит не UTF-8", flag.to_ascii_uppercase()))?;
+        if !value.is_empty() {
+            tokens.push(format!("--{}={}", flag, value));
+        }
+    }
+    Ok(())
+}
+
+fn push_flags(table: &Table, place: &str, tokens: &mut Vec<String>) -> Result<(), String> {
+    for (key, value) in table {
+        let key = key.replace('_', "-");
+        if key == "profile" {
+            return Err(format!("{}: профили не могут ссылаться на другие профили", place));
+        }
+        // Как и в ENV_FLAGS: сид в файле незаметно сделал бы все пароли предсказуемыми
+        if key == "seed" {
+            return Err(format!("{}: ключ 'seed' не задается в файле настроек, только флагом --seed", place));
+        }
+        let flag = format!("--{}", key);
+        if let Some(&(_, negation)) = SWITCHES.iter().find(|(name, _)| *name == key) {
+            match value {
+                Value::Boolean(true) => tokens.push(flag),
+                // `false` в профиле перекрывает `true` из верхнего уровня файла
+                Value::Boolean(false) => tokens.extend(negation.map(|n| format!("--{}", n))),
+                _ => return Err(format!("{}: ключ '{}' принимает только true или false", place, key)),
+            }
+        } else if OPTIONS.contains(&key.as_str()) {
+            match value {
+                Value::String(s) => tokens.push(format!("{}={}", flag, s)),
+                Value::Integer(n) => tokens.push(format!("{}={}", flag, n)),
+                Value::Float(f) => tokens.push(format!("{}={}", flag, f)),
+                _ => return Err(format!("{}: ключ '{}' должен быть строкой или числом", place, key)),
+            }
+        } else {
+            return Err(format!(
+                "{}: неизвестный ключ '{}'. Ключи — длинные имена флагов без '--', например length = 20",
+                place, key
+            ));
+        }
+    }
+    Ok(())
+}
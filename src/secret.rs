@@ -0,0 +1,243 @@
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use zeroize::Zeroize;
+
+/// Сгенерированный пароль. Не реализует `Display`, а `Debug` скрывает значение,
+/// чтобы пароль не попал в логи случайно. Доступ — только через `expose`.
+/// Память закреплена в RAM (mlock), как у `SecretBuf`, и при освобождении
+/// затирается нулями.
+pub struct Secret {
+    value: String,
+    locked: bool,
+}
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.value
+    }
+
+    /// Забирает строку. Дальше затирание — забота вызывающего
+    pub fn into_string(mut self) -> String {
+        self.unlock();
+        std::mem::take(&mut self.value)
+    }
+
+    fn unlock(&mut self) {
+        if std::mem::take(&mut self.locked) {
+            unlock_memory(self.value.as_ptr(), self.value.capacity());
+        }
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        let locked = lock_memory(value.as_ptr(), value.capacity());
+        Secret { value, locked }
+    }
+}
+
+impl Clone for Secret {
+    fn clone(&self) -> Self {
+        Secret::from(self.value.clone())
+    }
+}
+
+impl PartialEq for Secret {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl Eq for Secret {}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.value.zeroize();
+        self.unlock();
+    }
+}
+
+/// Буфер чанка с паролями: закреплен в RAM (mlock), чтобы не уйти в swap,
+/// и затирается нулями целиком, включая свободную емкость, при освобождении.
+pub struct SecretBuf {
+    buf: Vec<u8>,
+    locked: bool,
+}
+
+impl SecretBuf {
+    pub fn with_capacity(capacity: usize) -> Self {
+        let buf = Vec::with_capacity(capacity);
+        let locked = lock_memory(buf.as_ptr(), buf.capacity());
+        SecretBuf { buf, locked }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.buf.as_mut_ptr()
+    }
+
+    /// # Safety
+    /// Первые `len` байт должны быть записаны, `len` не больше емкости.
+    /// Буфер никогда не растет, иначе mlock остался бы на старом адресе
+    pub unsafe fn set_len(&mut self, len: usize) {
+        debug_assert!(len <= self.buf.capacity());
+        unsafe { self.buf.set_len(len) }
+    }
+
+    /// Дописывает байты. Как и `set_len`, не дает буферу вырасти
+    pub fn extend_from_slice(&mut self, data: &[u8]) {
+        assert!(data.len() <= self.buf.capacity() - self.buf.len(), "SecretBuf не растет");
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Опустошает буфер для повторного использования. Старые байты остаются
+    /// в закрепленной памяти до перезаписи и затираются в `Drop`
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+}
+
+/// Запас mlock-буферов одной емкости для чанков. Выделять, закреплять,
+/// открепять и затирать буфер на каждый чанк в 32 КБ — это несколько системных
+/// вызовов и лишний проход по памяти, поэтому буферы возвращаются сюда
+/// и берутся снова. Все оставшиеся буферы затираются вместе с пулом.
+pub struct SecretPool {
+    capacity: usize,
+    limit: usize,
+    free: Mutex<Vec<SecretBuf>>,
+}
+
+impl SecretPool {
+    /// Пул буферов по `capacity` байт, хранящий не больше `limit` свободных
+    pub fn new(capacity: usize, limit: usize) -> Self {
+        SecretPool {
+            capacity,
+            limit,
+            free: Mutex::new(Vec::with_capacity(limit)),
+        }
+    }
+
+    pub fn take(&self) -> SecretBuf {
+        let buf = self.free.lock().unwrap().pop();
+        buf.unwrap_or_else(|| SecretBuf::with_capacity(self.capacity))
+    }
+
+    /// Возвращает буфер в пул. Лишние сверх `limit` освобождаются сразу
+    pub fn put(&self, mut buf: SecretBuf) {
+        buf.clear();
+        let mut free = self.free.lock().unwrap();
+        if free.len() < self.limit && buf.capacity() >= self.capacity {
+            free.push(buf);
+        }
+    }
+}
+
+impl Deref for SecretBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl AsRef<[u8]> for SecretBuf {
+    fn as_ref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl Drop for SecretBuf {
+    fn drop(&mut self) {
+        let (ptr, capacity) = (self.buf.as_ptr(), self.buf.capacity());
+        self.buf.zeroize();
+        if self.locked {
+            unlock_memory(ptr, capacity);
+        }
+    }
+}
+
+static MLOCK_WARNED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+fn lock_memory(ptr: *const u8, len: usize) -> bool {
+    if len == 0 {
+        return false;
+    }
+    let ok = unsafe { libc::mlock(ptr as *const libc::c_void, len) } == 0;
+    // Обычно упирается в RLIMIT_MEMLOCK. Затирание все равно работает
+    if !ok && !MLOCK_WARNED.swap(true, Ordering::Relaxed) {
+        eprintln!(
+            "Предупреждение: mlock недоступен (RLIMIT_MEMLOCK?), буферы с паролями могут попасть в swap"
+        );
+    }
+    ok
+}
+
+#[cfg(unix)]
+fn unlock_memory(ptr: *const u8, len: usize) {
+    unsafe {
+        libc::munlock(ptr as *const libc::c_void, len);
+    }
+}
+
+#[cfg(not(unix))]
+fn lock_memory(_ptr: *const u8, _len: usize) -> bool {
+    let _ = &MLOCK_WARNED;
+    false
+}
+
+#[cfg(not(unix))]
+fn unlock_memory(_ptr: *const u8, _len: usize) {}
+
+/// Запрещает дампы памяти процесса: иначе пароли из RAM могут оказаться
+/// в core-файле на диске. Вызывать до начала генерации.
+pub fn disable_core_dumps() {
+    #[cfg(unix)]
+    unsafe {
+        let limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        libc::setrlimit(libc::RLIMIT_CORE, &limit);
+    }
+    // На Linux это же закрывает доступ через ptrace и /proc/<pid>/mem для других процессов
+    #[cfg(target_os = "linux")]
+    unsafe {
+        libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_reuses_buffers() {
+        let pool = SecretPool::new(64, 1);
+        let mut buf = pool.take();
+        buf.extend_from_slice(b"secret");
+        let ptr = buf.as_mut_ptr();
+        pool.put(buf);
+
+        let mut again = pool.take();
+        assert_eq!(again.as_mut_ptr(), ptr);
+        assert!(again.is_empty());
+        assert!(again.capacity() >= 64);
+        // Второй буфер сверх лимита пула просто освобождается
+        let extra = pool.take();
+        pool.put(again);
+        pool.put(extra);
+        assert_eq!(pool.free.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,388 @@
+use crate::words::WORDLIST;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+/// Оценка стойкости в духе zxcvbn: пароль разбивается на узнаваемые куски
+/// (слова, клавиатурные ряды, повторы, последовательности, даты), и берется
+/// разбиение с наименьшим числом попыток для атакующего.
+pub struct Estimate {
+    /// log2 числа попыток перебора
+    pub bits: f64,
+    /// 0 (угадывается сразу) … 4 (очень стойкий), пороги как у zxcvbn
+    pub score: u8,
+    /// Виды найденных кусков в порядке следования, например `["dictionary", "date"]`
+    pub patterns: Vec<&'static str>,
+}
+
+/// Ряды клавиатуры QWERTY для поиска клавиатурных последовательностей
+const KEYBOARD_ROWS: [&str; 4] = ["`1234567890-=", "qwertyuiop[]\\", "asdfghjkl;'", "zxcvbnm,./"];
+/// Разброс лет, который перебирают атакующие для дат
+const YEAR_SPAN: f64 = 120.0;
+const MIN_WORD: usize = 3;
+/// Сколько символов разбирается на шаблоны, как в zxcvbn
+const MAX_ANALYZED: usize = 100;
+
+struct Match {
+    start: usize,
+    end: usize,
+    bits: f64,
+    kind: &'static str,
+}
+
+pub fn estimate(password: &str) -> Estimate {
+    // Как и zxcvbn, оцениваем только начало: разбор растет как n³, а оценка
+    // без хвоста лишь занижена — для проверки стойкости это безопасная сторона
+    let chars: Vec<char> = password.chars().take(MAX_ANALYZED).collect();
+    let (bits, patterns) = analyze(&chars, Some(&mut HashMap::new()));
+
+    // 10^3, 10^6, 10^8, 10^10 попыток
+    let score = match bits {
+        b if b < 9.97 => 0,
+        b if b < 19.93 => 1,
+        b if b < 26.58 => 2,
+        b if b < 33.22 => 3,
+        _ => 4,
+    };
+    Estimate {
+        bits,
+        score,
+        patterns,
+    }
+}
+
+/// Разбиение с наименьшим числом бит. `units` — кэш оценок звеньев повторов;
+/// без него повторы не ищутся, так звено оценивается без рекурсии
+fn analyze(chars: &[char], units: Option<&mut HashMap<Vec<char>, f64>>) -> (f64, Vec<&'static str>) {
+    let n = chars.len();
+
+    let mut matches = Vec::new();
+    dictionary_matches(chars, &mut matches);
+    keyboard_matches(chars, &mut matches);
+    if let Some(units) = units {
+        repeat_matches(chars, units, &mut matches);
+    }
+    sequence_matches(chars, &mut matches);
+    date_matches(chars, &mut matches);
+
+    // best[j] — минимум бит для префикса длины j; from[j] — чем он закрыт
+    let mut best = vec![f64::INFINITY; n + 1];
+    let mut from: Vec<Option<usize>> = vec![None; n + 1];
+    best[0] = 0.0;
+    for j in 1..=n {
+        best[j] = best[j - 1] + bruteforce_bits(chars[j - 1]);
+        for (idx, m) in matches.iter().enumerate() {
+            if m.end == j && best[m.start] + m.bits < best[j] {
+                best[j] = best[m.start] + m.bits;
+                from[j] = Some(idx);
+            }
+        }
+    }
+
+    let mut patterns = Vec::new();
+    let mut j = n;
+    while j > 0 {
+        match from[j] {
+            Some(idx) => {
+                patterns.push(matches[idx].kind);
+                j = matches[idx].start;
+            }
+            None => {
+                if patterns.last() != Some(&"bruteforce") {
+                    patterns.push("bruteforce");
+                }
+                j -= 1;
+            }
+        }
+    }
+    patterns.reverse();
+    (best[n], patterns)
+}
+
+/// Перебор одного символа: размер его класса
+fn bruteforce_bits(c: char) -> f64 {
+    let size: f64 = if c.is_ascii_lowercase() || c.is_ascii_uppercase() {
+        26.0
+    } else if c.is_ascii_digit() {
+        10.0
+    } else {
+        // Спецсимволы ASCII; буквы других алфавитов (кириллица) — того же порядка
+        33.0
+    };
+    size.log2()
+}
+
+fn dictionary() -> &'static HashSet<&'static str> {
+    static SET: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    SET.get_or_init(|| WORDLIST.iter().copied().collect())
+}
+
+/// Обратная замена l33t: 4 → a, 3 → e, 0 → o и т. д.
+fn unleet(c: char) -> char {
+    match c {
+        '4' | '@' => 'a',
+        '3' => 'e',
+        '1' | '!' => 'i',
+        '0' => 'o',
+        '$' | '5' => 's',
+        '7' => 't',
+        c => c,
+    }
+}
+
+fn dictionary_matches(chars: &[char], out: &mut Vec<Match>) {
+    let dict = dictionary();
+    let longest = WORDLIST.iter().map(|w| w.len()).max().unwrap_or(0);
+    let base = (WORDLIST.len() as f64).log2();
+
+    for start in 0..chars.len() {
+        for end in start + MIN_WORD..=chars.len().min(start + longest) {
+            let token = &chars[start..end];
+            let lower: String = token.iter().flat_map(|c| c.to_lowercase()).collect();
+            let unleeted: String = lower.chars().map(unleet).collect();
+            let reversed: String = lower.chars().rev().collect();
+
+            // l33t-замены и запись задом наперед добавляют по биту
+            let extra = if dict.contains(lower.as_str()) {
+                0.0
+            } else if dict.contains(unleeted.as_str()) || dict.contains(reversed.as_str()) {
+                1.0
+            } else {
+                continue;
+            };
+            out.push(Match {
+                start,
+                end,
+                bits: base + extra + case_bits(token),
+                kind: "dictionary",
+            });
+        }
+    }
+}
+
+/// Варианты регистра: «Слово» и «СЛОВО» — один бит, иначе сумма сочетаний как в zxcvbn
+fn case_bits(token: &[char]) -> f64 {
+    let upper = token.iter().filter(|c| c.is_uppercase()).count();
+    let lower = token.iter().filter(|c| c.is_lowercase()).count();
+    if upper == 0 {
+        return 0.0;
+    }
+    if lower == 0 || (upper == 1 && token[0].is_uppercase()) {
+        return 1.0;
+    }
+    let total = upper + lower;
+    let variants: f64 = (1..=upper.min(lower)).map(|k| binomial(total, k)).sum();
+    variants.log2()
+}
+
+fn binomial(n: usize, k: usize) -> f64 {
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+}
+
+fn keyboard_matches(chars: &[char], out: &mut Vec<Match>) {
+    let lower: Vec<char> = chars.iter().map(|c| c.to_ascii_lowercase()).collect();
+    let starts: f64 = KEYBOARD_ROWS.iter().map(|r| r.len()).sum::<usize>() as f64;
+
+    for row in KEYBOARD_ROWS {
+        let row: Vec<char> = row.chars().collect();
+        let pos = |c: char| row.iter().position(|&r| r == c);
+        let mut i = 0;
+        while i < lower.len() {
+            let mut end = i + 1;
+            let mut step = 0i64;
+            if let Some(p0) = pos(lower[i]) {
+                let mut prev = p0 as i64;
+                while end < lower.len() {
+                    let Some(p) = pos(lower[end]) else { break };
+                    let d = p as i64 - prev;
+                    if !(d == 1 || d == -1) || (step != 0 && d != step) {
+                        break;
+                    }
+                    step = d;
+                    prev = p as i64;
+                    end += 1;
+                }
+            }
+            if end - i >= MIN_WORD {
+                out.push(Match {
+                    start: i,
+                    end,
+                    // Начальная клавиша, направление и длина
+                    bits: (starts * 2.0 * (end - i) as f64).log2(),
+                    kind: "keyboard",
+                });
+                i = end;
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+fn repeat_matches(chars: &[char], units: &mut HashMap<Vec<char>, f64>, out: &mut Vec<Match>) {
+    let n = chars.len();
+    for start in 0..n {
+        for block in 1..=(n - start) / 2 {
+            let unit = &chars[start..start + block];
+            let mut reps = 1;
+            while start + (reps + 1) * block <= n
+                && &chars[start + reps * block..start + (reps + 1) * block] == unit
+            {
+                reps += 1;
+            }
+            // «aa» — еще не повтор, «aaa» и «abab» — уже
+            if reps < 2 || (block == 1 && reps < 3) {
+                continue;
+            }
+            let unit_bits = match units.get(unit) {
+                Some(&bits) => bits,
+                None => {
+                    let bits = analyze(unit, None).0;
+                    units.insert(unit.to_vec(), bits);
+                    bits
+                }
+            };
+            out.push(Match {
+                start,
+                end: start + reps * block,
+                bits: unit_bits + (reps as f64).log2(),
+                kind: "repeat",
+            });
+        }
+    }
+}
+
+fn sequence_matches(chars: &[char], out: &mut Vec<Match>) {
+    let class = |c: char| {
+        if c.is_ascii_lowercase() {
+            1
+        } else if c.is_ascii_uppercase() {
+            2
+        } else if c.is_ascii_digit() {
+            3
+        } else {
+            0
+        }
+    };
+
+    let mut i = 0;
+    while i + 1 < chars.len() {
+        let delta = chars[i + 1] as i64 - chars[i] as i64;
+        let cls = class(chars[i]);
+        let mut end = i + 1;
+        if cls != 0 && (delta == 1 || delta == -1) {
+            while end < chars.len()
+                && class(chars[end]) == cls
+                && chars[end] as i64 - chars[end - 1] as i64 == delta
+            {
+                end += 1;
+            }
+        }
+        if end - i >= MIN_WORD {
+            // Последовательности от начала алфавита угадывают первыми
+            let base: f64 = if "aAzZ019".contains(chars[i]) {
+                4.0
+            } else if cls == 3 {
+                10.0
+            } else {
+                26.0
+            };
+            let descending = if delta < 0 { 1.0 } else { 0.0 };
+            out.push(Match {
+                start: i,
+                end,
+                bits: (base * (end - i) as f64).log2() + descending,
+                kind: "sequence",
+            });
+            i = end - 1;
+        } else {
+            i += 1;
+        }
+    }
+}
+
+fn date_matches(chars: &[char], out: &mut Vec<Match>) {
+    let n = chars.len();
+    for start in 0..n {
+        for end in start + 4..=n.min(start + 10) {
+            let token: String = chars[start..end].iter().collect();
+            if let Some(bits) = date_bits(&token) {
+                out.push(Match {
+                    start,
+                    end,
+                    bits,
+                    kind: "date",
+                });
+            }
+        }
+    }
+}
+
+/// Год, дата без разделителей (ДДММГГГГ, ГГГГММДД, ММДДГГ…) или с разделителем
+fn date_bits(token: &str) -> Option<f64> {
+    let full = (365.0 * YEAR_SPAN).log2();
+    if token.bytes().all(|b| b.is_ascii_digit()) {
+        // Только цифры, поэтому разбор не падает
+        let d = |r: std::ops::Range<usize>| token[r].parse::<u32>().unwrap_or(0);
+        let valid = match token.len() {
+            4 => return is_year(d(0..4)).then_some(YEAR_SPAN.log2()),
+            // ДДММГГ, ММДДГГ, ГГММДД
+            6 => {
+                is_day_month(d(0..2), d(2..4))
+                    || is_day_month(d(2..4), d(0..2))
+                    || is_day_month(d(4..6), d(2..4))
+            }
+            // ДДММГГГГ, ММДДГГГГ, ГГГГММДД
+            8 => {
+                (is_year(d(4..8)) && (is_day_month(d(0..2), d(2..4)) || is_day_month(d(2..4), d(0..2))))
+                    || (is_year(d(0..4)) && is_day_month(d(6..8), d(4..6)))
+            }
+            _ => false,
+        };
+        return valid.then_some(full);
+    }
+
+    let sep = token.chars().find(|c| !c.is_ascii_digit())?;
+    if !"/-._ ".contains(sep) {
+        return None;
+    }
+    let parts: Vec<&str> = token.split(sep).collect();
+    if parts.len() != 3 || parts.iter().any(|p| p.is_empty() || !p.bytes().all(|b| b.is_ascii_digit())) {
+        return None;
+    }
+    let nums: Vec<u32> = parts.iter().filter_map(|p| p.parse().ok()).collect();
+    let valid = if parts[0].len() == 4 {
+        is_year(nums[0]) && is_day_month(nums[2], nums[1])
+    } else if parts[2].len() == 4 || parts[2].len() == 2 {
+        let year_ok = parts[2].len() == 2 || is_year(nums[2]);
+        year_ok && (is_day_month(nums[0], nums[1]) || is_day_month(nums[1], nums[0]))
+    } else {
+        false
+    };
+    // Разделитель выбирается из нескольких, это пара бит сверху
+    valid.then_some(full + 2.0)
+}
+
+fn is_year(y: u32) -> bool {
+    (1900..2040).contains(&y)
+}
+
+fn is_day_month(day: u32, month: u32) -> bool {
+    (1..=31).contains(&day) && (1..=12).contains(&month)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_repeats_stay_fast() {
+        // Раньше звено повтора оценивалось рекурсивно, и 128 × `a` не укладывалось в минуты
+        for n in [64, 128, 1000] {
+            let e = estimate(&"a".repeat(n));
+            assert_eq!(e.patterns, ["repeat"]);
+            assert!(e.bits < 16.0, "{} × a: {} бит", n, e.bits);
+        }
+        let e = estimate(&"abcd".repeat(50));
+        assert_eq!(e.patterns, ["repeat"]);
+    }
+}
@@ -1,3 +1,6 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+
 pub const WORDLIST: &[&str] = &[
     "ability", "about", "above", "absolute", "access", "account", "active", "actual", 
     "adopt", "adult", "advice", "after", "again", "agent", "agree", "ahead", "alarm", 
@@ -217,4 +220,191 @@ pub const WORDLIST: &[&str] = &[
     "write", "writer", "wrong", "yard", "yarn", "year", "yearly", "yeast", "yellow", 
     "yester", "yet", "yield", "yoga", "yogurt", "young", "your", "youth", "zebra", 
     "zero", "zest", "zinc", "zone", "zoo", "zoom"
-];
\ No newline at end of file
+];
+
+/// Словарь для режима фраз: встроенный `WORDLIST` или загруженный из файла.
+/// `Cow` позволяет не копировать встроенные слова.
+#[derive(Clone)]
+pub struct Wordlist {
+    pub words: Vec<Cow<'static, str>>,
+}
+
+impl Default for Wordlist {
+    fn default() -> Self {
+        Wordlist {
+            words: WORDLIST.iter().map(|&w| Cow::Borrowed(w)).collect(),
+        }
+    }
+}
+
+/// Встроенные словари, выбираемые по имени через `--wordlist`
+const BUNDLED: &[(&str, &str)] = &[
+    ("bip39", include_str!("wordlists/bip39.txt")),
+    ("ru", include_str!("wordlists/ru.txt")),
+    ("ru-translit", include_str!("wordlists/ru_translit.txt")),
+];
+
+/// Списки EFF: имя для `--wordlist` и имя файла на eff.org.
+/// Файлов списков нет в дереве исходников, поэтому они пока не встроены
+const EFF: &[(&str, &str)] = &[
+    ("eff-large", "eff_large_wordlist.txt"),
+    ("eff-short", "eff_short_wordlist_1.txt"),
+];
+
+impl Wordlist {
+    /// Встроенный словарь по имени. `None` — такого имени нет, значит это путь к файлу
+    pub fn bundled(name: &str) -> Option<Result<Self, String>> {
+        if name == "default" {
+            return Some(Ok(Wordlist::default()));
+        }
+        if let Some(&(_, file)) = EFF.iter().find(|(n, _)| *n == name) {
+            return Some(Err(format!(
+                "словарь '{}' не встроен в эту сборку. Скачайте {} с https://www.eff.org/dice \
+                 и передайте путь к файлу: --wordlist {}",
+                name, file, file
+            )));
+        }
+        let (_, text) = BUNDLED.iter().find(|(n, _)| *n == name)?;
+        Some(Ok(Wordlist {
+            words: text.lines().map(Cow::Borrowed).collect(),
+        }))
+    }
+
+    /// Имя встроенного словаря или путь к файлу
+    pub fn open(name_or_path: &str) -> Result<Self, String> {
+        Wordlist::bundled(name_or_path).unwrap_or_else(|| Wordlist::load(name_or_path))
+    }
+
+    /// Загружает словарь: одно слово на строку или формат diceware (`11111 word`).
+    /// Пустые строки пропускаются, повторы удаляются с предупреждением.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("не удалось прочитать словарь '{}': {}", path, e))?;
+
+        let mut seen = HashSet::new();
+        let mut words = Vec::new();
+        let mut duplicates = 0;
+
+        for (line_no, line) in text.lines().enumerate() {
+            let mut parts = line.split_whitespace();
+            let word = match (parts.next(), parts.next()) {
+                (None, _) => continue,
+                // Diceware: номер из цифр костей, затем слово
+                (Some(first), Some(second)) if first.bytes().all(|b| b.is_ascii_digit()) => second,
+                (Some(first), None) => first,
+                _ => {
+                    return Err(format!(
+                        "строка {} словаря '{}' содержит пробелы внутри слова",
+                        line_no + 1,
+                        path
+                    ));
+                }
+            };
+            if parts.next().is_some() {
+                return Err(format!(
+                    "строка {} словаря '{}' содержит лишние поля",
+                    line_no + 1,
+                    path
+                ));
+            }
+            if seen.insert(word) {
+                words.push(Cow::Owned(word.to_string()));
+            } else {
+                duplicates += 1;
+            }
+        }
+
+        if duplicates > 0 {
+            eprintln!(
+                "Предупреждение: в словаре '{}' удалено повторов: {}",
+                path, duplicates
+            );
+        }
+        if words.len() < 2 {
+            return Err(format!("словарь '{}' должен содержать минимум 2 слова", path));
+        }
+
+        let list = Wordlist { words };
+        if !list.is_uniquely_decodable() {
+            eprintln!(
+                "Предупреждение: словарь '{}' не однозначно декодируем при склейке слов без разделителя",
+                path
+            );
+        }
+        Ok(list)
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    pub fn longest(&self) -> usize {
+        self.words.iter().map(|w| w.len()).max().unwrap_or(0)
+    }
+
+    /// Тест Сардинаса — Паттерсона: можно ли однозначно разрезать склейку слов.
+    /// Если нет, фраза без разделителей может совпасть с другой фразой,
+    /// и реальная энтропия окажется ниже расчетной.
+    pub fn is_uniquely_decodable(&self) -> bool {
+        let mut sorted: Vec<&str> = self.words.iter().map(|w| w.as_ref()).collect();
+        sorted.sort_unstable();
+        let set: HashSet<&str> = sorted.iter().copied().collect();
+
+        // Все слова, начинающиеся с prefix (кроме него самого) — по отсортированному списку
+        let extensions = |prefix: &str| -> Vec<&str> {
+            let start = sorted.partition_point(|w| *w < prefix);
+            sorted[start..]
+                .iter()
+                .take_while(|w| w.starts_with(prefix))
+                .filter(|w| w.len() > prefix.len())
+                .map(|w| &w[prefix.len()..])
+                .collect()
+        };
+
+        // Первое множество «висящих» суффиксов: слово — префикс другого слова
+        let mut current: HashSet<&str> = HashSet::new();
+        for w in &sorted {
+            current.extend(extensions(w));
+        }
+
+        let mut visited: HashSet<&str> = HashSet::new();
+        while !current.is_empty() {
+            let mut next = HashSet::new();
+            for s in current {
+                if set.contains(s) {
+                    return false;
+                }
+                if !visited.insert(s) {
+                    continue;
+                }
+                // Слово — префикс суффикса
+                for end in 1..s.len() {
+                    if s.is_char_boundary(end) && set.contains(&s[..end]) {
+                        next.insert(&s[end..]);
+                    }
+                }
+                // Суффикс — префикс слова
+                next.extend(extensions(s));
+            }
+            current = next;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eff_names_point_to_the_file() {
+        for (name, file) in EFF {
+            let error = Wordlist::open(name).err().unwrap();
+            assert!(error.contains(file), "{}", error);
+        }
+    }
+}
@@ -1,17 +1,227 @@
+use crate::secret::SecretBuf;
+use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::{self, BufWriter, Write};
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(PartialEq, Clone, Copy)]
-pub enum OutputFormat { Plain, Json, Csv }
+pub enum OutputFormat {
+    Plain,
+    Json,
+    JsonObjects,
+    Ndjson,
+    Csv,
+    KeepassCsv,
+    KeepassXml,
+    Bitwarden,
+    OnePasswordCsv,
+}
 
-/// Создает буферизированный поток вывода.
-/// 1MB для stdout и 32MB для файла, чтобы реже дергать диск.
+impl OutputFormat {
+    /// Форматы, которые пишут один JSON-массив на весь вывод
+    pub fn is_json_array(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Json | OutputFormat::JsonObjects | OutputFormat::Bitwarden
+        )
+    }
+}
+
+/// Размер буфера вывода: чанки по 32 КБ копятся в нем, чтобы не делать
+/// системный вызов на каждый чанк
+const OUT_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Создает поток вывода. Буферизацию делает `LockedWriter`, а не `BufWriter`:
+/// тот держал бы копию паролей вне mlock и без затирания.
+/// stdout открывается напрямую: `io::stdout()` еще и буферизует строки.
 pub fn get_writer(out_file: &Option<String>) -> io::Result<Box<dyn Write + Send>> {
     if let Some(path) = out_file {
-        let file = File::create(path)?;
-        Ok(Box::new(BufWriter::with_capacity(32 * 1024 * 1024, file)))
-    } else {
-        // Увеличиваем до 4 МБ для чистого вывода в терминал / dev / null
-        Ok(Box::new(BufWriter::with_capacity(4 * 1024 * 1024, io::stdout())))
+        return Ok(Box::new(LockedWriter::new(File::create(path)?)));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::fd::AsFd;
+        let fd = io::stdout().as_fd().try_clone_to_owned()?;
+        Ok(Box::new(LockedWriter::new(File::from(fd))))
+    }
+    #[cfg(not(unix))]
+    Ok(Box::new(LockedWriter::new(io::stdout())))
+}
+
+/// Аналог `BufWriter` с буфером в `SecretBuf`: закреплен в RAM и затирается
+/// при освобождении. Как и `BufWriter`, сбрасывает остаток при удалении
+pub struct LockedWriter<W: Write> {
+    out: W,
+    buf: SecretBuf,
+}
+
+impl<W: Write> LockedWriter<W> {
+    pub fn new(out: W) -> Self {
+        LockedWriter {
+            out,
+            buf: SecretBuf::with_capacity(OUT_BUFFER_SIZE),
+        }
+    }
+
+    fn flush_buf(&mut self) -> io::Result<()> {
+        let result = self.out.write_all(&self.buf);
+        self.buf.clear();
+        result
+    }
+}
+
+impl<W: Write> Write for LockedWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if data.len() > self.buf.capacity() - self.buf.len() {
+            self.flush_buf()?;
+        }
+        // Крупные куски идут мимо буфера, как в `BufWriter`
+        if data.len() >= self.buf.capacity() {
+            return self.out.write(data);
+        }
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_buf()?;
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Drop for LockedWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.flush_buf();
+    }
+}
+
+/// Буфер переупорядочивания: чанки приходят из потоков rayon в любом порядке,
+/// а в поток уходят строго по номеру. Обычный `Write` пишет сразу, мимо очереди.
+pub struct ReorderBuffer<W: Write, T: AsRef<[u8]> = Vec<u8>> {
+    out: W,
+    next: u64,
+    pending: BTreeMap<u64, T>,
+}
+
+impl<W: Write, T: AsRef<[u8]>> ReorderBuffer<W, T> {
+    pub fn new(out: W) -> Self {
+        ReorderBuffer {
+            out,
+            next: 0,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Принимает чанк с номером `idx`. Если он следующий по порядку — пишет его
+    /// и все накопившиеся за ним, иначе откладывает до прихода предшественников.
+    /// Записанные чанки отдаются в `written`, например обратно в пул буферов
+    pub fn push(&mut self, idx: u64, data: T, mut written: impl FnMut(T)) -> io::Result<()> {
+        if idx != self.next {
+            self.pending.insert(idx, data);
+            return Ok(());
+        }
+        self.out.write_all(data.as_ref())?;
+        written(data);
+        self.next += 1;
+        while let Some(data) = self.pending.remove(&self.next) {
+            self.out.write_all(data.as_ref())?;
+            written(data);
+            self.next += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write, T: AsRef<[u8]>> Write for ReorderBuffer<W, T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Экранирует строку для вставки внутрь JSON-строки
+pub fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Заголовок `--json-objects`: кто и когда сгенерировал пароли.
+/// Массив `passwords` закрывается в конце вывода через `]}`
+pub fn json_objects_header(rounds: u8) -> String {
+    format!(
+        "{{\"generator\":\"{}\",\"version\":\"{}\",\"rounds\":{},\"timestamp\":\"{}\",\"passwords\":[",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        rounds,
+        rfc3339_utc(SystemTime::now())
+    )
+}
+
+/// Время в RFC 3339 (UTC) без сторонних зависимостей
+fn rfc3339_utc(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, rem) = (secs / 86400, secs % 86400);
+
+    // Перевод числа дней в гражданскую дату (алгоритм Говарда Хиннанта)
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locked_writer_keeps_order_across_flushes() {
+        let mut expected = Vec::new();
+        let mut out = LockedWriter::new(Vec::new());
+        // Мелкие куски копятся, кусок больше буфера идет напрямую
+        for (i, size) in [10, OUT_BUFFER_SIZE - 5, 7, 2 * OUT_BUFFER_SIZE, 3].into_iter().enumerate() {
+            let piece = vec![b'a' + i as u8; size];
+            out.write_all(&piece).unwrap();
+            expected.extend_from_slice(&piece);
+        }
+        out.flush().unwrap();
+        assert!(out.out == expected);
+    }
+
+    #[test]
+    fn reorder_buffer_returns_written_chunks() {
+        let mut written = Vec::new();
+        let mut out = ReorderBuffer::new(Vec::new());
+        out.push(1, b"b".to_vec(), |c| written.push(c)).unwrap();
+        assert!(written.is_empty());
+        out.push(0, b"a".to_vec(), |c| written.push(c)).unwrap();
+        out.push(2, b"c".to_vec(), |c| written.push(c)).unwrap();
+        assert_eq!(written, [b"a", b"b", b"c"]);
+        assert_eq!(out.out, b"abc");
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,100 @@
+//! Буфер обмена через настоящий бинарник: `-c` запускает фоновый процесс,
+//! который держит буфер. Тест с Xvfb помечен `#[ignore]`: он нужен Xvfb и xclip,
+//! запуск — `cargo test -- --ignored`
+#![cfg(target_os = "linux")]
+
+use std::path::Path;
+use std::process::{Child, Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+const SEED: &str = "c0ffee";
+
+fn passwg(display: Option<&str>, args: &[&str]) -> Output {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_passwg"));
+    cmd.args(args).env_remove("WAYLAND_DISPLAY").env("XDG_CONFIG_HOME", "/nonexistent");
+    match display {
+        Some(display) => cmd.env("DISPLAY", display),
+        None => cmd.env_remove("DISPLAY"),
+    };
+    cmd.output().unwrap()
+}
+
+#[test]
+fn copy_without_display_is_an_error() {
+    let out = passwg(None, &["-c"]);
+    assert_eq!(out.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("буфер обмена недоступен"), "{}", stderr);
+}
+
+/// Xvfb на свободном номере дисплея; убивается при удалении
+struct Xvfb {
+    child: Child,
+    display: String,
+}
+
+impl Xvfb {
+    fn start() -> Xvfb {
+        let n = (90..190)
+            .find(|n| !Path::new(&format!("/tmp/.X11-unix/X{}", n)).exists())
+            .expect("нет свободного номера дисплея");
+        let display = format!(":{}", n);
+        let child = Command::new("Xvfb")
+            .args([display.as_str(), "-nolisten", "tcp"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Xvfb не запустился");
+        let xvfb = Xvfb { child, display };
+        let socket = format!("/tmp/.X11-unix/X{}", n);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !Path::new(&socket).exists() {
+            assert!(Instant::now() < deadline, "Xvfb не открыл {}", socket);
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        xvfb
+    }
+
+    /// Содержимое буфера обмена, прочитанное отдельным процессом xclip
+    fn read(&self) -> String {
+        let out = Command::new("xclip")
+            .args(["-o", "-selection", "clipboard"])
+            .env("DISPLAY", &self.display)
+            .output()
+            .expect("xclip не запустился");
+        String::from_utf8(out.stdout).unwrap()
+    }
+}
+
+impl Drop for Xvfb {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+#[ignore = "нужны Xvfb и xclip"]
+fn copy_and_clear_under_xvfb() {
+    let xvfb = Xvfb::start();
+    let display = Some(xvfb.display.as_str());
+    let printed = passwg(display, &["--seed", SEED, "20"]);
+    let expected = String::from_utf8(printed.stdout).unwrap().trim_end().to_string();
+    assert_eq!(expected.len(), 20);
+    let read = || xvfb.read();
+
+    let out = passwg(display, &["--seed", SEED, "20", "-c"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert_eq!(String::from_utf8_lossy(&out.stdout).trim_end(), expected);
+    assert_eq!(read(), expected);
+
+    let out = passwg(display, &["--seed", SEED, "20", "-c", "--clear-after", "1"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert_eq!(read(), expected);
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while read() == expected {
+        assert!(Instant::now() < deadline, "буфер не очищен через --clear-after");
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    assert_eq!(read(), "");
+}